@@ -1,9 +1,22 @@
 use anchor_lang::prelude::*;
+use anchor_lang::solana_program::ed25519_program;
+use anchor_lang::solana_program::sysvar::instructions::{
+    load_current_index_checked, load_instruction_at_checked,
+};
 use anchor_spl::associated_token::AssociatedToken;
 use anchor_spl::token::{self, Mint, Token, TokenAccount, Transfer};
 
 declare_id!("31f4RcqyuAjnMz6AZZbZ6Tt7VUMjENHc5rSP8MYMc3Qt");
 
+pub const MAX_PAYOUT_SPLITS: usize = 5;
+pub const MAX_FEE_TIERS: usize = 8;
+pub const MAX_RENTAL_DAYS: i64 = 366;
+pub const MAX_COUPON_CODE_LEN: usize = 32;
+pub const MAX_INSTALLMENTS: u8 = 24;
+pub const SECONDS_PER_HOUR: i64 = 60 * 60;
+pub const SECONDS_PER_DAY: i64 = 24 * SECONDS_PER_HOUR;
+pub const BOND_WITHDRAWAL_WINDOW: i64 = 2 * SECONDS_PER_DAY;
+
 #[program]
 pub mod kairoria_rental {
     use super::*;
@@ -13,823 +26,7379 @@ pub mod kairoria_rental {
         global_state.admin = admin;
         global_state.platform_fee_rate = 1000;
         global_state.bump = ctx.bumps.global_state;
+        global_state.set_defaults();
         
         msg!("Kairoria Rental System initialized with admin: {}", admin);
         Ok(())
     }
 
-    pub fn create_rental_transaction(
-        ctx: Context<CreateRentalTransaction>,
-        product_id: u64,
-        owner_wallet: Pubkey,
-        total_amount: u64,
-        rental_start: i64,
-        rental_end: i64,
-        booking_id: String,
-    ) -> Result<()> {
-        require!(total_amount > 0, ErrorCode::InvalidAmount);
-        require!(rental_end > rental_start, ErrorCode::InvalidRentalPeriod);
-        require!(booking_id.len() <= 64, ErrorCode::BookingIdTooLong);
+    pub fn migrate_global_state(ctx: Context<MigrateGlobalState>) -> Result<()> {
+        let global_state = ctx.accounts.global_state.to_account_info();
 
-        let rental_transaction = &mut ctx.accounts.rental_transaction;
-        rental_transaction.product_id = product_id;
-        rental_transaction.renter = ctx.accounts.renter.key();
-        rental_transaction.owner_wallet = owner_wallet;
-        rental_transaction.total_amount = total_amount;
-        rental_transaction.rental_start = rental_start;
-        rental_transaction.rental_end = rental_end;
-        rental_transaction.booking_id = booking_id;
-        rental_transaction.status = TransactionStatus::Created;
-        rental_transaction.created_at = Clock::get()?.unix_timestamp;
-        rental_transaction.bump = ctx.bumps.rental_transaction;
+        {
+            let data = global_state.try_borrow_data()?;
+            require!(
+                data.len() >= GlobalState::LEGACY_SPACE && data.len() < GlobalState::SPACE,
+                ErrorCode::GlobalStateAlreadyMigrated
+            );
+            require!(
+                data[..8] == *GlobalState::DISCRIMINATOR,
+                ErrorCode::InvalidConfigAccount
+            );
+            let admin = Pubkey::try_from(&data[8..40])
+                .map_err(|_| ErrorCode::InvalidConfigAccount)?;
+            require_keys_eq!(admin, ctx.accounts.admin.key(), ErrorCode::UnauthorizedAdmin);
+        }
 
-        emit!(RentalTransactionCreated {
-            product_id,
-            renter: ctx.accounts.renter.key(),
-            owner_wallet,
-            total_amount,
-            booking_id: rental_transaction.booking_id.clone(),
-        });
+        // The grown tail is zero-filled, which deserializes as an all-zero configuration
+        // until the defaults are written over it
+        grow_account(
+            &global_state,
+            &ctx.accounts.admin,
+            &ctx.accounts.system_program,
+            GlobalState::SPACE,
+        )?;
+        let mut data = global_state.try_borrow_mut_data()?;
+        let mut state = GlobalState::try_deserialize(&mut &data[..])?;
+        state.set_defaults();
+        state.try_serialize(&mut &mut data[..])?;
 
+        msg!("Global state migrated to {} bytes", GlobalState::SPACE);
         Ok(())
     }
 
-    pub fn pay_rental(ctx: Context<PayRental>, amount: u64) -> Result<()> {
-        let rental_transaction = &mut ctx.accounts.rental_transaction;
-        
+    pub fn initialize_treasury(ctx: Context<InitializeTreasury>) -> Result<()> {
         require!(
-            rental_transaction.status == TransactionStatus::Created,
-            ErrorCode::InvalidTransactionStatus
+            ctx.accounts.admin.key() == ctx.accounts.global_state.admin,
+            ErrorCode::UnauthorizedAdmin
         );
+
+        msg!("Treasury initialized for mint: {}", ctx.accounts.usdc_mint.key());
+        Ok(())
+    }
+
+    pub fn add_treasury_destination(ctx: Context<AddTreasuryDestination>) -> Result<()> {
         require!(
-            amount == rental_transaction.total_amount,
-            ErrorCode::IncorrectPaymentAmount
+            ctx.accounts.admin.key() == ctx.accounts.global_state.admin,
+            ErrorCode::UnauthorizedAdmin
         );
 
-        let transfer_accounts = Transfer {
-            from: ctx.accounts.renter_token_account.to_account_info(),
-            to: ctx.accounts.escrow_token_account.to_account_info(),
-            authority: ctx.accounts.renter.to_account_info(),
-        };
+        let treasury_destination = &mut ctx.accounts.treasury_destination;
+        treasury_destination.token_account = ctx.accounts.destination_token_account.key();
+        treasury_destination.mint = ctx.accounts.destination_token_account.mint;
+        treasury_destination.bump = ctx.bumps.treasury_destination;
+
+        Ok(())
+    }
 
-        let cpi_ctx = CpiContext::new(
-            ctx.accounts.token_program.to_account_info(),
-            transfer_accounts,
+    pub fn remove_treasury_destination(ctx: Context<RemoveTreasuryDestination>) -> Result<()> {
+        require!(
+            ctx.accounts.admin.key() == ctx.accounts.global_state.admin,
+            ErrorCode::UnauthorizedAdmin
         );
 
-        token::transfer(cpi_ctx, amount)?;
+        Ok(())
+    }
 
-        rental_transaction.status = TransactionStatus::Paid;
-        rental_transaction.paid_at = Some(Clock::get()?.unix_timestamp);
-        rental_transaction.escrow_bump = ctx.bumps.escrow_token_account;
+    pub fn withdraw_treasury(ctx: Context<WithdrawTreasury>, amount: u64) -> Result<()> {
+        require!(
+            ctx.accounts.admin.key() == ctx.accounts.global_state.admin,
+            ErrorCode::UnauthorizedAdmin
+        );
+        require!(amount > 0, ErrorCode::InvalidAmount);
+        require!(
+            amount <= ctx.accounts.treasury_token_account.amount,
+            ErrorCode::InsufficientTreasuryBalance
+        );
 
-        emit!(RentalPaymentCompleted {
-            booking_id: rental_transaction.booking_id.clone(),
+        transfer_from_treasury(
+            &ctx.accounts.treasury_token_account,
+            ctx.accounts.destination_token_account.to_account_info(),
+            &ctx.accounts.global_state,
+            &ctx.accounts.token_program,
             amount,
-            renter: ctx.accounts.renter.key(),
+        )?;
+
+        emit!(TreasuryWithdrawal {
+            mint: ctx.accounts.usdc_mint.key(),
+            destination: ctx.accounts.destination_token_account.key(),
+            amount,
+            admin: ctx.accounts.admin.key(),
+            withdrawn_at: Clock::get()?.unix_timestamp,
         });
 
         Ok(())
     }
 
-    pub fn complete_rental(ctx: Context<CompleteRental>) -> Result<()> {
-        let current_time = Clock::get()?.unix_timestamp;
-        let signer = ctx.accounts.signer.key();
-
+    pub fn set_fee_rates(
+        ctx: Context<UpdateGlobalState>,
+        host_fee_rate: u16,
+        service_fee_rate: u16,
+    ) -> Result<()> {
         require!(
-            ctx.accounts.rental_transaction.status == TransactionStatus::Paid,
-            ErrorCode::InvalidTransactionStatus
+            ctx.accounts.admin.key() == ctx.accounts.global_state.admin,
+            ErrorCode::UnauthorizedAdmin
         );
-
         require!(
-            signer == ctx.accounts.rental_transaction.renter ||
-            signer == ctx.accounts.rental_transaction.owner_wallet ||
-            signer == ctx.accounts.global_state.admin,
-            ErrorCode::UnauthorizedCompletion
+            host_fee_rate <= 10000 && service_fee_rate <= 10000,
+            ErrorCode::InvalidPercentages
         );
 
-        let grace_period = 24 * 60 * 60;
-        let completion_allowed_time = ctx.accounts.rental_transaction.rental_end + grace_period;
-        
+        let global_state = &mut ctx.accounts.global_state;
+        global_state.platform_fee_rate = host_fee_rate;
+        global_state.service_fee_rate = service_fee_rate;
+
+        msg!("Fee rates set - host: {} bps, service: {} bps", host_fee_rate, service_fee_rate);
+        Ok(())
+    }
+
+    pub fn set_deposit_terms(
+        ctx: Context<UpdateGlobalState>,
+        min_deposit_rate: u16,
+        balance_due_period: i64,
+    ) -> Result<()> {
         require!(
-            current_time >= completion_allowed_time || 
-            signer == ctx.accounts.rental_transaction.renter,
-            ErrorCode::CompletionNotAllowed
+            ctx.accounts.admin.key() == ctx.accounts.global_state.admin,
+            ErrorCode::UnauthorizedAdmin
         );
+        require!(min_deposit_rate <= 10000, ErrorCode::InvalidPercentages);
+        require!(balance_due_period >= 0, ErrorCode::InvalidRentalPeriod);
 
-        let global_state = &ctx.accounts.global_state;
-        let total_amount = ctx.accounts.rental_transaction.total_amount;
-        
-        let platform_fee = total_amount
-            .checked_mul(global_state.platform_fee_rate as u64)
-            .ok_or(ErrorCode::MathOverflow)?
-            .checked_div(10000)
-            .ok_or(ErrorCode::MathOverflow)?;
-        
-        let owner_amount = total_amount
-            .checked_sub(platform_fee)
-            .ok_or(ErrorCode::MathOverflow)?;
-
-        let seeds = &[
-            b"rental_transaction",
-            &ctx.accounts.rental_transaction.product_id.to_le_bytes()[..8],
-            &ctx.accounts.rental_transaction.renter.to_bytes(),
-            &[ctx.accounts.rental_transaction.bump],
-        ];
-        let pda_signer_seeds = &[&seeds[..]];
+        let global_state = &mut ctx.accounts.global_state;
+        global_state.min_deposit_rate = min_deposit_rate;
+        global_state.balance_due_period = balance_due_period;
 
-        // Transfer to owner
-        let transfer_to_owner = Transfer {
-            from: ctx.accounts.escrow_token_account.to_account_info(),
-            to: ctx.accounts.owner_token_account.to_account_info(),
-            authority: ctx.accounts.rental_transaction.to_account_info(),
-        };
+        msg!(
+            "Deposit terms set - minimum deposit: {} bps, balance due {}s before start",
+            min_deposit_rate,
+            balance_due_period
+        );
+        Ok(())
+    }
 
-        let cpi_ctx_owner = CpiContext::new_with_signer(
-            ctx.accounts.token_program.to_account_info(),
-            transfer_to_owner,
-            pda_signer_seeds,
+    pub fn set_installment_grace_period(
+        ctx: Context<UpdateGlobalState>,
+        installment_grace_period: i64,
+    ) -> Result<()> {
+        require!(
+            ctx.accounts.admin.key() == ctx.accounts.global_state.admin,
+            ErrorCode::UnauthorizedAdmin
         );
+        require!(installment_grace_period >= 0, ErrorCode::InvalidRentalPeriod);
 
-        token::transfer(cpi_ctx_owner, owner_amount)?;
+        ctx.accounts.global_state.installment_grace_period = installment_grace_period;
 
-        // Transfer platform fee to admin
-        let transfer_to_admin = Transfer {
-            from: ctx.accounts.escrow_token_account.to_account_info(),
-            to: ctx.accounts.admin_token_account.to_account_info(),
-            authority: ctx.accounts.rental_transaction.to_account_info(),
-        };
+        msg!("Installment grace period set to {}s", installment_grace_period);
+        Ok(())
+    }
 
-        let cpi_ctx_admin = CpiContext::new_with_signer(
-            ctx.accounts.token_program.to_account_info(),
-            transfer_to_admin,
-            pda_signer_seeds,
+    pub fn set_no_show_windows(
+        ctx: Context<UpdateGlobalState>,
+        no_show_window: i64,
+        no_show_contest_window: i64,
+    ) -> Result<()> {
+        require!(
+            ctx.accounts.admin.key() == ctx.accounts.global_state.admin,
+            ErrorCode::UnauthorizedAdmin
+        );
+        require!(
+            no_show_window >= 0 && no_show_contest_window >= 0,
+            ErrorCode::InvalidRentalPeriod
         );
 
-        token::transfer(cpi_ctx_admin, platform_fee)?;
-
-        let rental_transaction = &mut ctx.accounts.rental_transaction;
-        rental_transaction.status = TransactionStatus::Completed;
-        rental_transaction.completed_at = Some(current_time);
-
-        emit!(RentalCompleted {
-            booking_id: rental_transaction.booking_id.clone(),
-            owner_amount,
-            platform_fee,
-            completed_by: signer,
-        });
+        let global_state = &mut ctx.accounts.global_state;
+        global_state.no_show_window = no_show_window;
+        global_state.no_show_contest_window = no_show_contest_window;
 
+        msg!(
+            "No-show windows set - claim after {}s past start, contest within {}s",
+            no_show_window,
+            no_show_contest_window
+        );
         Ok(())
     }
 
-    pub fn admin_intervene(
-        ctx: Context<AdminIntervene>,
-        owner_percentage: u16,
-        renter_refund_percentage: u16,
-        reason: String,
+    pub fn set_owner_bond_terms(
+        ctx: Context<UpdateGlobalState>,
+        owner_penalty_window: i64,
+        owner_penalty_rate: u16,
+        bond_withdrawal_cooldown: i64,
     ) -> Result<()> {
         require!(
             ctx.accounts.admin.key() == ctx.accounts.global_state.admin,
             ErrorCode::UnauthorizedAdmin
         );
-        let total_percentage = owner_percentage
-            .checked_add(renter_refund_percentage)
-            .ok_or(ErrorCode::MathOverflow)?;
+        require!(owner_penalty_rate <= 10000, ErrorCode::InvalidPercentages);
         require!(
-            total_percentage <= 10000,
-            ErrorCode::InvalidPercentages
+            owner_penalty_window >= 0 && bond_withdrawal_cooldown >= 0,
+            ErrorCode::InvalidRentalPeriod
         );
-        require!(reason.len() <= 256, ErrorCode::ReasonTooLong);
 
+        let global_state = &mut ctx.accounts.global_state;
+        global_state.owner_penalty_window = owner_penalty_window;
+        global_state.owner_penalty_rate = owner_penalty_rate;
+        global_state.bond_withdrawal_cooldown = bond_withdrawal_cooldown;
+
+        msg!(
+            "Owner bond terms set - penalty: {} bps within {}s of start, withdrawal cooldown: {}s",
+            owner_penalty_rate,
+            owner_penalty_window,
+            bond_withdrawal_cooldown
+        );
+        Ok(())
+    }
+
+    pub fn set_settlement_fee_mode(
+        ctx: Context<UpdateGlobalState>,
+        settlement_fee_mode: SettlementFeeMode,
+    ) -> Result<()> {
         require!(
-            ctx.accounts.rental_transaction.status == TransactionStatus::Paid,
-            ErrorCode::InvalidTransactionStatus
+            ctx.accounts.admin.key() == ctx.accounts.global_state.admin,
+            ErrorCode::UnauthorizedAdmin
         );
 
-        let total_amount = ctx.accounts.rental_transaction.total_amount;
-        
-        let owner_amount = total_amount
-            .checked_mul(owner_percentage as u64)
-            .ok_or(ErrorCode::MathOverflow)?
-            .checked_div(10000)
-            .ok_or(ErrorCode::MathOverflow)?;
-        
-        let renter_refund = total_amount
-            .checked_mul(renter_refund_percentage as u64)
-            .ok_or(ErrorCode::MathOverflow)?
-            .checked_div(10000)
-            .ok_or(ErrorCode::MathOverflow)?;
-        
-        let platform_fee = total_amount
-            .checked_sub(owner_amount)
-            .ok_or(ErrorCode::MathOverflow)?
-            .checked_sub(renter_refund)
-            .ok_or(ErrorCode::MathOverflow)?;
+        ctx.accounts.global_state.settlement_fee_mode = settlement_fee_mode;
 
-        let seeds = &[
-            b"rental_transaction",
-            &ctx.accounts.rental_transaction.product_id.to_le_bytes()[..8],
-            &ctx.accounts.rental_transaction.renter.to_bytes(),
-            &[ctx.accounts.rental_transaction.bump],
-        ];
-        let pda_signer_seeds = &[&seeds[..]];
+        msg!("Settlement fee mode updated");
+        Ok(())
+    }
 
-        if owner_amount > 0 {
-            let transfer_to_owner = Transfer {
-                from: ctx.accounts.escrow_token_account.to_account_info(),
-                to: ctx.accounts.owner_token_account.to_account_info(),
-                authority: ctx.accounts.rental_transaction.to_account_info(),
-            };
+    pub fn set_quote_signer(ctx: Context<UpdateGlobalState>, quote_signer: Pubkey) -> Result<()> {
+        require!(
+            ctx.accounts.admin.key() == ctx.accounts.global_state.admin,
+            ErrorCode::UnauthorizedAdmin
+        );
 
-            let cpi_ctx_owner = CpiContext::new_with_signer(
-                ctx.accounts.token_program.to_account_info(),
-                transfer_to_owner,
-                pda_signer_seeds,
-            );
+        ctx.accounts.global_state.quote_signer = quote_signer;
 
-            token::transfer(cpi_ctx_owner, owner_amount)?;
-        }
+        msg!("Quote signer set to: {}", quote_signer);
+        Ok(())
+    }
 
-        if renter_refund > 0 {
-            let transfer_to_renter = Transfer {
-                from: ctx.accounts.escrow_token_account.to_account_info(),
-                to: ctx.accounts.renter_token_account.to_account_info(),
-                authority: ctx.accounts.rental_transaction.to_account_info(),
-            };
+    pub fn set_referral_fee_share(
+        ctx: Context<UpdateGlobalState>,
+        referral_fee_share: u16,
+    ) -> Result<()> {
+        require!(
+            ctx.accounts.admin.key() == ctx.accounts.global_state.admin,
+            ErrorCode::UnauthorizedAdmin
+        );
+        require!(referral_fee_share <= 10000, ErrorCode::InvalidPercentages);
 
-            let cpi_ctx_renter = CpiContext::new_with_signer(
-                ctx.accounts.token_program.to_account_info(),
-                transfer_to_renter,
-                pda_signer_seeds,
-            );
+        ctx.accounts.global_state.referral_fee_share = referral_fee_share;
 
-            token::transfer(cpi_ctx_renter, renter_refund)?;
-        }
+        msg!("Referral fee share set to {} bps", referral_fee_share);
+        Ok(())
+    }
 
-        if platform_fee > 0 {
-            let transfer_to_admin = Transfer {
-                from: ctx.accounts.escrow_token_account.to_account_info(),
-                to: ctx.accounts.admin_token_account.to_account_info(),
-                authority: ctx.accounts.rental_transaction.to_account_info(),
-            };
+    pub fn set_fee_clamp(
+        ctx: Context<SetFeeClamp>,
+        min_fee: u64,
+        max_fee: u64,
+    ) -> Result<()> {
+        require!(
+            ctx.accounts.admin.key() == ctx.accounts.global_state.admin,
+            ErrorCode::UnauthorizedAdmin
+        );
+        require!(max_fee == 0 || max_fee >= min_fee, ErrorCode::InvalidFeeClamp);
 
-            let cpi_ctx_admin = CpiContext::new_with_signer(
-                ctx.accounts.token_program.to_account_info(),
-                transfer_to_admin,
-                pda_signer_seeds,
-            );
+        let fee_clamp = &mut ctx.accounts.fee_clamp;
+        fee_clamp.mint = ctx.accounts.usdc_mint.key();
+        fee_clamp.min_fee = min_fee;
+        fee_clamp.max_fee = max_fee;
+        fee_clamp.bump = ctx.bumps.fee_clamp;
 
-            token::transfer(cpi_ctx_admin, platform_fee)?;
-        }
+        msg!("Fee clamp for mint {} set to [{}, {}]", fee_clamp.mint, min_fee, max_fee);
+        Ok(())
+    }
 
-        let rental_transaction = &mut ctx.accounts.rental_transaction;
-        rental_transaction.status = TransactionStatus::Resolved;
-        rental_transaction.completed_at = Some(Clock::get()?.unix_timestamp);
-        rental_transaction.resolution_reason = Some(reason.clone());
+    pub fn set_fee_tiers(ctx: Context<SetFeeTiers>, tiers: Vec<FeeTier>) -> Result<()> {
+        require!(
+            ctx.accounts.admin.key() == ctx.accounts.global_state.admin,
+            ErrorCode::UnauthorizedAdmin
+        );
+        require!(tiers.len() <= MAX_FEE_TIERS, ErrorCode::TooManyFeeTiers);
 
-        emit!(AdminIntervention {
-            booking_id: rental_transaction.booking_id.clone(),
-            owner_amount,
-            renter_refund,
-            platform_fee,
-            reason,
-            admin: ctx.accounts.admin.key(),
-        });
+        for (index, tier) in tiers.iter().enumerate() {
+            require!(tier.platform_fee_rate <= 10000, ErrorCode::InvalidPercentages);
+            if index > 0 {
+                require!(
+                    tier.min_volume > tiers[index - 1].min_volume,
+                    ErrorCode::InvalidFeeTiers
+                );
+            }
+        }
+
+        let fee_tiers = &mut ctx.accounts.fee_tiers;
+        fee_tiers.tiers = tiers;
+        fee_tiers.bump = ctx.bumps.fee_tiers;
 
+        msg!("Fee tiers updated: {} tiers", fee_tiers.tiers.len());
         Ok(())
     }
 
-    pub fn cancel_as_renter_created(ctx: Context<CancelAsRenterCreated>) -> Result<()> {
-        let rental_transaction = &mut ctx.accounts.rental_transaction;
-        let current_time = Clock::get()?.unix_timestamp;
-        
-        require!(
-            ctx.accounts.renter.key() == rental_transaction.renter,
-            ErrorCode::UnauthorizedCancellation
-        );
+    pub fn set_category_fee(
+        ctx: Context<SetCategoryFee>,
+        category_id: u64,
+        platform_fee_rate: u16,
+    ) -> Result<()> {
         require!(
-            rental_transaction.status == TransactionStatus::Created,
-            ErrorCode::InvalidTransactionStatus
+            ctx.accounts.admin.key() == ctx.accounts.global_state.admin,
+            ErrorCode::UnauthorizedAdmin
         );
+        require!(platform_fee_rate <= 10000, ErrorCode::InvalidPercentages);
 
-        rental_transaction.status = TransactionStatus::Cancelled;
-        rental_transaction.completed_at = Some(current_time);
-
-        emit!(RentalCancelledByRenter {
-            booking_id: rental_transaction.booking_id.clone(),
-            renter: ctx.accounts.renter.key(),
-            cancelled_at: current_time,
-        });
+        let category_fee = &mut ctx.accounts.category_fee;
+        category_fee.category_id = category_id;
+        category_fee.platform_fee_rate = platform_fee_rate;
+        category_fee.bump = ctx.bumps.category_fee;
 
+        msg!("Category {} fee rate set to {} bps", category_id, platform_fee_rate);
         Ok(())
     }
 
-    pub fn cancel_as_renter_paid(ctx: Context<CancelAsRenterPaid>) -> Result<()> {
-        let rental_transaction = &mut ctx.accounts.rental_transaction;
-        let current_time = Clock::get()?.unix_timestamp;
-        
+    pub fn remove_category_fee(ctx: Context<RemoveCategoryFee>) -> Result<()> {
         require!(
-            ctx.accounts.renter.key() == rental_transaction.renter,
-            ErrorCode::UnauthorizedCancellation
+            ctx.accounts.admin.key() == ctx.accounts.global_state.admin,
+            ErrorCode::UnauthorizedAdmin
         );
-        require!(
-            rental_transaction.status == TransactionStatus::Paid,
-            ErrorCode::InvalidTransactionStatus
+
+        msg!("Category {} fee rate removed", ctx.accounts.category_fee.category_id);
+        Ok(())
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    pub fn create_coupon(
+        ctx: Context<CreateCoupon>,
+        code: String,
+        discount: CouponDiscount,
+        max_uses: u32,
+        per_wallet_limit: u32,
+        expires_at: i64,
+        product_id: Option<u64>,
+        category_id: Option<u64>,
+    ) -> Result<()> {
+        require!(
+            !code.is_empty() && code.len() <= MAX_COUPON_CODE_LEN,
+            ErrorCode::InvalidCouponCode
         );
+        require!(max_uses > 0 && per_wallet_limit > 0, ErrorCode::InvalidCoupon);
         require!(
-            current_time < rental_transaction.rental_start,
-            ErrorCode::CancellationTooLate
+            expires_at > Clock::get()?.unix_timestamp,
+            ErrorCode::InvalidCoupon
         );
+        match discount {
+            CouponDiscount::Percent { bps } => {
+                require!(bps > 0 && bps <= 10000, ErrorCode::InvalidPercentages)
+            }
+            CouponDiscount::Fixed { amount } => require!(amount > 0, ErrorCode::InvalidAmount),
+        }
 
-        // Calculate refund amount based on timing
-        let time_until_rental = rental_transaction.rental_start - current_time;
-        let one_day_in_seconds = 24 * 60 * 60;
-        let refund_percentage = if time_until_rental >= one_day_in_seconds {
-            10000u64 // 100%
+        // Coupons issued by the admin are funded by the platform, all others by the issuing owner
+        let issuer = ctx.accounts.issuer.key();
+        let funded_by = if issuer == ctx.accounts.global_state.admin {
+            DiscountBearer::Platform
         } else {
-            5000u64 // 50%
+            DiscountBearer::Owner
         };
 
-        let total_amount = rental_transaction.total_amount;
-        let refund_amount = total_amount
-            .checked_mul(refund_percentage)
-            .ok_or(ErrorCode::MathOverflow)?
-            .checked_div(10000)
-            .ok_or(ErrorCode::MathOverflow)?;
+        let coupon = &mut ctx.accounts.coupon;
+        coupon.code = code;
+        coupon.issuer = issuer;
+        coupon.funded_by = funded_by;
+        coupon.discount = discount;
+        coupon.max_uses = max_uses;
+        coupon.uses = 0;
+        coupon.per_wallet_limit = per_wallet_limit;
+        coupon.expires_at = expires_at;
+        coupon.product_id = product_id;
+        coupon.category_id = category_id;
+        coupon.bump = ctx.bumps.coupon;
 
-        let product_id = rental_transaction.product_id;
-        let renter_key = rental_transaction.renter;
-        let bump = rental_transaction.bump;
-        
-        let seeds = &[
-            b"rental_transaction",
-            &product_id.to_le_bytes()[..8],
-            &renter_key.to_bytes(),
-            &[bump],
-        ];
-        let pda_signer_seeds = &[&seeds[..]];
+        emit!(CouponCreated {
+            code: coupon.code.clone(),
+            issuer,
+            funded_by: coupon.funded_by.clone(),
+            discount: coupon.discount.clone(),
+            max_uses,
+            expires_at,
+        });
 
-        if refund_amount > 0 {
-            let transfer_refund = Transfer {
-                from: ctx.accounts.escrow_token_account.to_account_info(),
-                to: ctx.accounts.renter_token_account.to_account_info(),
-                authority: rental_transaction.to_account_info(),
-            };
+        Ok(())
+    }
 
-            let cpi_ctx = CpiContext::new_with_signer(
-                ctx.accounts.token_program.to_account_info(),
-                transfer_refund,
-                pda_signer_seeds,
-            );
+    pub fn register_referrer(ctx: Context<RegisterReferrer>) -> Result<()> {
+        let referrer_account = &mut ctx.accounts.referrer_account;
+        referrer_account.wallet = ctx.accounts.wallet.key();
+        referrer_account.referral_count = 0;
+        referrer_account.total_earned = 0;
+        referrer_account.registered_at = Clock::get()?.unix_timestamp;
+        referrer_account.bump = ctx.bumps.referrer_account;
 
-            token::transfer(cpi_ctx, refund_amount)?;
-        }
+        emit!(ReferrerRegistered {
+            wallet: referrer_account.wallet,
+            registered_at: referrer_account.registered_at,
+        });
 
-        let remaining_amount = total_amount
-            .checked_sub(refund_amount)
-            .ok_or(ErrorCode::MathOverflow)?;
+        Ok(())
+    }
 
-        if remaining_amount > 0 {
-            let transfer_fee = Transfer {
-                from: ctx.accounts.escrow_token_account.to_account_info(),
-                to: ctx.accounts.admin_token_account.to_account_info(),
-                authority: rental_transaction.to_account_info(),
-            };
+    pub fn create_product(
+        ctx: Context<CreateProduct>,
+        product_id: u64,
+        rates: ProductRates,
+        category_id: Option<u64>,
+        policies: ProductPolicies,
+    ) -> Result<()> {
+        rates.validate()?;
+        policies.validate()?;
 
-            let cpi_ctx_fee = CpiContext::new_with_signer(
-                ctx.accounts.token_program.to_account_info(),
-                transfer_fee,
-                pda_signer_seeds,
+        let product = &mut ctx.accounts.product;
+        product.product_id = product_id;
+        product.owner = ctx.accounts.owner.key();
+        product.rates = rates;
+        product.category_id = category_id;
+        product.policies = policies;
+        product.bump = ctx.bumps.product;
+
+        emit!(ProductRatesUpdated {
+            product_id,
+            owner: product.owner,
+            rates: product.rates.clone(),
+        });
+
+        Ok(())
+    }
+
+    pub fn update_product_rates(ctx: Context<UpdateProduct>, rates: ProductRates) -> Result<()> {
+        require!(
+            ctx.accounts.owner.key() == ctx.accounts.product.owner,
+            ErrorCode::UnauthorizedProductUpdate
+        );
+        rates.validate()?;
+
+        let product = &mut ctx.accounts.product;
+        product.rates = rates;
+
+        emit!(ProductRatesUpdated {
+            product_id: product.product_id,
+            owner: product.owner,
+            rates: product.rates.clone(),
+        });
+
+        Ok(())
+    }
+
+    pub fn update_product_policies(
+        ctx: Context<UpdateProduct>,
+        policies: ProductPolicies,
+    ) -> Result<()> {
+        require!(
+            ctx.accounts.owner.key() == ctx.accounts.product.owner,
+            ErrorCode::UnauthorizedProductUpdate
+        );
+        policies.validate()?;
+
+        let product = &mut ctx.accounts.product;
+        product.policies = policies;
+
+        emit!(ProductPoliciesUpdated {
+            product_id: product.product_id,
+            owner: product.owner,
+            policies: product.policies.clone(),
+        });
+
+        Ok(())
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    pub fn create_rental_transaction(
+        ctx: Context<CreateRentalTransaction>,
+        product_id: u64,
+        owner_wallet: Pubkey,
+        total_amount: u64,
+        rental_start: i64,
+        rental_end: i64,
+        booking_id: String,
+        referrer: Option<Pubkey>,
+        category_id: Option<u64>,
+        quote_expires_at: Option<i64>,
+    ) -> Result<()> {
+        RentalTransaction::validate_booking(
+            ctx.accounts.renter.key(),
+            owner_wallet,
+            total_amount,
+            rental_start,
+            rental_end,
+            &booking_id,
+        )?;
+
+        let product = load_config::<Product>(&ctx.accounts.product)?;
+        let category_id = verify_booking_price(
+            product.as_ref(),
+            &ctx.accounts.global_state,
+            &ctx.accounts.instructions_sysvar,
+            product_id,
+            owner_wallet,
+            total_amount,
+            rental_start,
+            rental_end,
+            category_id,
+            quote_expires_at,
+        )?;
+
+        record_referral(
+            ctx.accounts.referrer_account.as_mut(),
+            referrer,
+            ctx.accounts.renter.key(),
+            owner_wallet,
+        )?;
+
+        let rental_transaction = &mut ctx.accounts.rental_transaction;
+        rental_transaction.initialize(
+            product_id,
+            ctx.accounts.renter.key(),
+            owner_wallet,
+            total_amount,
+            rental_start,
+            rental_end,
+            booking_id,
+            ctx.bumps.rental_transaction,
+        )?;
+        rental_transaction.referrer = referrer;
+        rental_transaction.category_id = category_id;
+        rental_transaction.policies = Product::policies_snapshot(product.as_ref());
+
+        ctx.accounts.renter_profile.init_if_empty(ctx.accounts.renter.key(), ctx.bumps.renter_profile);
+        ctx.accounts.owner_profile.init_if_empty(owner_wallet, ctx.bumps.owner_profile);
+        ctx.accounts.owner_profile.lock_bond_until(rental_start);
+
+        emit!(RentalTransactionCreated {
+            product_id,
+            renter: ctx.accounts.renter.key(),
+            owner_wallet,
+            total_amount,
+            booking_id: rental_transaction.booking_id.clone(),
+        });
+
+        Ok(())
+    }
+
+    // Rentals created before profiles existed reference wallets without one, so anyone
+    // can create the missing profile before completing or cancelling such a rental
+    pub fn init_user_profile(ctx: Context<InitUserProfile>, wallet: Pubkey) -> Result<()> {
+        ctx.accounts.user_profile.init_if_empty(wallet, ctx.bumps.user_profile);
+        Ok(())
+    }
+
+    pub fn migrate_rental_transaction(ctx: Context<MigrateRentalTransaction>) -> Result<()> {
+        let rental_transaction = ctx.accounts.rental_transaction.to_account_info();
+
+        {
+            let data = rental_transaction.try_borrow_data()?;
+            require!(
+                data.len() >= RentalTransaction::LEGACY_SPACE && data.len() < RentalTransaction::SPACE,
+                ErrorCode::RentalAlreadyMigrated
+            );
+            require!(
+                data[..8] == *RentalTransaction::DISCRIMINATOR,
+                ErrorCode::InvalidRentalAccount
             );
+        }
+
+        // The grown tail is zero-filled, so fields added since the rental was created
+        // deserialize as unset
+        grow_account(
+            &rental_transaction,
+            &ctx.accounts.payer,
+            &ctx.accounts.system_program,
+            RentalTransaction::SPACE,
+        )?;
+        let mut data = rental_transaction.try_borrow_mut_data()?;
+        let mut rental = RentalTransaction::try_deserialize(&mut &data[..])?;
+        rental.set_legacy_defaults();
+        rental.try_serialize(&mut &mut data[..])?;
+
+        Ok(())
+    }
+
+    pub fn migrate_user_profile(ctx: Context<MigrateUserProfile>) -> Result<()> {
+        let user_profile = ctx.accounts.user_profile.to_account_info();
 
-            token::transfer(cpi_ctx_fee, remaining_amount)?;
+        {
+            let data = user_profile.try_borrow_data()?;
+            require!(
+                data.len() >= UserProfile::LEGACY_SPACE && data.len() < UserProfile::SPACE,
+                ErrorCode::ProfileAlreadyMigrated
+            );
+            require!(
+                data[..8] == *UserProfile::DISCRIMINATOR,
+                ErrorCode::InvalidProfileAccount
+            );
         }
 
-        rental_transaction.status = TransactionStatus::Cancelled;
-        rental_transaction.completed_at = Some(current_time);
+        // The grown tail is zero-filled, so the bond lock starts out released. Owners
+        // could not post a bond before profiles had the lock.
+        grow_account(
+            &user_profile,
+            &ctx.accounts.payer,
+            &ctx.accounts.system_program,
+            UserProfile::SPACE,
+        )?;
 
-        emit!(RentalCancelledByRenterPaid {
+        Ok(())
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    pub fn book_and_pay(
+        ctx: Context<BookAndPay>,
+        product_id: u64,
+        owner_wallet: Pubkey,
+        total_amount: u64,
+        rental_start: i64,
+        rental_end: i64,
+        booking_id: String,
+        referrer: Option<Pubkey>,
+        category_id: Option<u64>,
+        quote_expires_at: Option<i64>,
+        coupon_key: Option<Pubkey>,
+    ) -> Result<()> {
+        let renter = ctx.accounts.renter.key();
+
+        RentalTransaction::validate_booking(
+            renter,
+            owner_wallet,
+            total_amount,
+            rental_start,
+            rental_end,
+            &booking_id,
+        )?;
+        require!(
+            coupon_key == ctx.accounts.coupon.as_ref().map(|coupon| coupon.key()),
+            ErrorCode::InvalidCoupon
+        );
+
+        let product = load_config::<Product>(&ctx.accounts.product)?;
+        let category_id = verify_booking_price(
+            product.as_ref(),
+            &ctx.accounts.global_state,
+            &ctx.accounts.instructions_sysvar,
+            product_id,
+            owner_wallet,
+            total_amount,
+            rental_start,
+            rental_end,
+            category_id,
+            quote_expires_at,
+        )?;
+
+        record_referral(
+            ctx.accounts.referrer_account.as_mut(),
+            referrer,
+            renter,
+            owner_wallet,
+        )?;
+
+        ctx.accounts.renter_profile.init_if_empty(renter, ctx.bumps.renter_profile);
+        ctx.accounts.owner_profile.init_if_empty(owner_wallet, ctx.bumps.owner_profile);
+        ctx.accounts.owner_profile.lock_bond_until(rental_start);
+
+        let rental_transaction = &mut ctx.accounts.rental_transaction;
+        rental_transaction.initialize(
+            product_id,
+            renter,
+            owner_wallet,
+            total_amount,
+            rental_start,
+            rental_end,
+            booking_id,
+            ctx.bumps.rental_transaction,
+        )?;
+        rental_transaction.referrer = referrer;
+        rental_transaction.category_id = category_id;
+        rental_transaction.policies = Product::policies_snapshot(product.as_ref());
+
+        emit!(RentalTransactionCreated {
+            product_id,
+            renter,
+            owner_wallet,
+            total_amount,
             booking_id: rental_transaction.booking_id.clone(),
-            renter: ctx.accounts.renter.key(),
-            refund_amount,
-            cancellation_fee: remaining_amount,
-            cancelled_at: current_time,
         });
 
+        let fee_clamp = load_config::<FeeClampConfig>(&ctx.accounts.fee_clamp)?;
+        collect_rental_payment(
+            rental_transaction,
+            ctx.accounts.coupon.as_deref_mut(),
+            ctx.accounts.coupon_redemption.as_deref_mut(),
+            ctx.bumps.coupon_redemption,
+            &ctx.accounts.renter_token_account,
+            &ctx.accounts.escrow_token_account,
+            ctx.bumps.escrow_token_account,
+            ctx.accounts.treasury_token_account.as_deref(),
+            &ctx.accounts.renter,
+            &ctx.accounts.global_state,
+            fee_clamp.as_ref(),
+            &ctx.accounts.token_program,
+        )?;
+
         Ok(())
     }
 
-    pub fn cancel_as_owner(ctx: Context<CancelAsOwner>) -> Result<()> {
-        let current_time = Clock::get()?.unix_timestamp;
+    pub fn pay_rental(
+        ctx: Context<PayRental>,
+        amount: u64,
+        coupon_key: Option<Pubkey>,
+    ) -> Result<()> {
+        let rental_transaction = &mut ctx.accounts.rental_transaction;
         
         require!(
-            ctx.accounts.rental_transaction.status == TransactionStatus::Paid,
+            rental_transaction.status == TransactionStatus::Created,
             ErrorCode::InvalidTransactionStatus
         );
         require!(
-            ctx.accounts.owner.key() == ctx.accounts.rental_transaction.owner_wallet,
-            ErrorCode::UnauthorizedOwnerCancellation
+            amount == rental_transaction.total_amount,
+            ErrorCode::IncorrectPaymentAmount
         );
-        
-        let one_day_in_seconds = 24 * 60 * 60;
-        let cancellation_deadline = ctx.accounts.rental_transaction.rental_start
-            .checked_sub(one_day_in_seconds)
+        // The redemption record is seeded from coupon_key, so it must name the coupon passed in
+        require!(
+            coupon_key == ctx.accounts.coupon.as_ref().map(|coupon| coupon.key()),
+            ErrorCode::InvalidCoupon
+        );
+
+        let fee_clamp = load_config::<FeeClampConfig>(&ctx.accounts.fee_clamp)?;
+        collect_rental_payment(
+            rental_transaction,
+            ctx.accounts.coupon.as_mut(),
+            ctx.accounts.coupon_redemption.as_mut(),
+            ctx.bumps.coupon_redemption,
+            &ctx.accounts.renter_token_account,
+            &ctx.accounts.escrow_token_account,
+            ctx.bumps.escrow_token_account,
+            ctx.accounts.treasury_token_account.as_ref(),
+            &ctx.accounts.renter,
+            &ctx.accounts.global_state,
+            fee_clamp.as_ref(),
+            &ctx.accounts.token_program,
+        )?;
+
+        Ok(())
+    }
+
+    pub fn pay_deposit(ctx: Context<PayPartial>, deposit_amount: u64) -> Result<()> {
+        let rental_transaction = &mut ctx.accounts.rental_transaction;
+        let global_state = &ctx.accounts.global_state;
+        let current_time = Clock::get()?.unix_timestamp;
+
+        require!(
+            rental_transaction.status == TransactionStatus::Created,
+            ErrorCode::InvalidTransactionStatus
+        );
+        require!(global_state.min_deposit_rate > 0, ErrorCode::PartialPaymentDisabled);
+
+        let total_amount = rental_transaction.total_amount;
+        let minimum_deposit = total_amount
+            .checked_mul(global_state.min_deposit_rate as u64)
+            .ok_or(ErrorCode::MathOverflow)?
+            .checked_div(10000)
             .ok_or(ErrorCode::MathOverflow)?;
-        
         require!(
-            current_time <= cancellation_deadline,
-            ErrorCode::OwnerCancellationTooLate
+            deposit_amount >= minimum_deposit && deposit_amount > 0 && deposit_amount < total_amount,
+            ErrorCode::IncorrectPaymentAmount
         );
-        
-        let seeds = &[
-            b"rental_transaction",
-            &ctx.accounts.rental_transaction.product_id.to_le_bytes()[..8],
-            &ctx.accounts.rental_transaction.renter.to_bytes(),
-            &[ctx.accounts.rental_transaction.bump],
-        ];
-        let pda_signer_seeds = &[&seeds[..]];
 
-        let transfer_refund = Transfer {
-            from: ctx.accounts.escrow_token_account.to_account_info(),
-            to: ctx.accounts.renter_token_account.to_account_info(),
-            authority: ctx.accounts.rental_transaction.to_account_info(),
-        };
+        let balance_due_at = rental_transaction.rental_start
+            .checked_sub(global_state.balance_due_period)
+            .ok_or(ErrorCode::MathOverflow)?;
+        require!(current_time < balance_due_at, ErrorCode::BalanceDeadlinePassed);
+
+        // The full service fee is collected with the deposit
+        let fee_clamp = load_config::<FeeClampConfig>(&ctx.accounts.fee_clamp)?;
+        let service_fee = calculate_service_fee(
+            total_amount,
+            global_state,
+            fee_clamp.as_ref(),
+        )?;
+        let amount_due = deposit_amount
+            .checked_add(service_fee)
+            .ok_or(ErrorCode::MathOverflow)?;
+
+        transfer_to_escrow(
+            &ctx.accounts.renter_token_account,
+            &ctx.accounts.escrow_token_account,
+            &ctx.accounts.renter,
+            &ctx.accounts.token_program,
+            amount_due,
+        )?;
+
+        rental_transaction.status = TransactionStatus::PartiallyPaid;
+        rental_transaction.escrow_bump = ctx.bumps.escrow_token_account;
+        rental_transaction.service_fee = service_fee;
+        rental_transaction.amount_paid = deposit_amount;
+        rental_transaction.balance_due_at = Some(balance_due_at);
 
-        let cpi_ctx = CpiContext::new_with_signer(
-            ctx.accounts.token_program.to_account_info(),
-            transfer_refund,
-            pda_signer_seeds,
+        emit!(RentalDepositPaid {
+            booking_id: rental_transaction.booking_id.clone(),
+            deposit_amount,
+            service_fee,
+            balance_due: total_amount - deposit_amount,
+            balance_due_at,
+            renter: ctx.accounts.renter.key(),
+        });
+
+        Ok(())
+    }
+
+    pub fn pay_balance(ctx: Context<PayBalance>) -> Result<()> {
+        let rental_transaction = &mut ctx.accounts.rental_transaction;
+        let current_time = Clock::get()?.unix_timestamp;
+
+        require!(
+            ctx.accounts.renter.key() == rental_transaction.renter,
+            ErrorCode::UnauthorizedCancellation
+        );
+        require!(
+            rental_transaction.status == TransactionStatus::PartiallyPaid,
+            ErrorCode::InvalidTransactionStatus
+        );
+        require!(
+            rental_transaction.balance_due_at.is_some_and(|due_at| current_time <= due_at),
+            ErrorCode::BalanceDeadlinePassed
         );
 
-        let total_amount = ctx.accounts.rental_transaction.total_amount;
-        token::transfer(cpi_ctx, total_amount)?;
-        
+        let balance = rental_transaction.total_amount
+            .checked_sub(rental_transaction.amount_paid)
+            .ok_or(ErrorCode::MathOverflow)?;
+
+        transfer_to_escrow(
+            &ctx.accounts.renter_token_account,
+            &ctx.accounts.escrow_token_account,
+            &ctx.accounts.renter,
+            &ctx.accounts.token_program,
+            balance,
+        )?;
+
+        rental_transaction.status = TransactionStatus::Paid;
+        rental_transaction.paid_at = Some(current_time);
+        rental_transaction.amount_paid = rental_transaction.total_amount;
+
+        emit!(RentalPaymentCompleted {
+            booking_id: rental_transaction.booking_id.clone(),
+            amount: balance,
+            service_fee: 0,
+            renter: ctx.accounts.renter.key(),
+        });
+
+        Ok(())
+    }
+
+    pub fn cancel_unpaid_balance(ctx: Context<CancelUnpaidBalance>) -> Result<()> {
         let rental_transaction = &mut ctx.accounts.rental_transaction;
+        let current_time = Clock::get()?.unix_timestamp;
+
+        require!(
+            rental_transaction.status == TransactionStatus::PartiallyPaid,
+            ErrorCode::InvalidTransactionStatus
+        );
+        require!(
+            rental_transaction.balance_due_at.is_some_and(|due_at| current_time > due_at),
+            ErrorCode::BalanceNotOverdue
+        );
+
+        let fee_clamp = load_config::<FeeClampConfig>(&ctx.accounts.fee_clamp)?;
+        let cancellation = rental_transaction.renter_cancellation(
+            current_time,
+            fee_clamp.as_ref(),
+        )?;
+
+        transfer_from_escrow(
+            rental_transaction,
+            &ctx.accounts.escrow_token_account,
+            ctx.accounts.renter_token_account.to_account_info(),
+            &ctx.accounts.token_program,
+            cancellation.renter_payout,
+        )?;
+
+        transfer_from_escrow(
+            rental_transaction,
+            &ctx.accounts.escrow_token_account,
+            ctx.accounts.treasury_token_account.to_account_info(),
+            &ctx.accounts.token_program,
+            cancellation.treasury_payout,
+        )?;
+
         rental_transaction.status = TransactionStatus::Cancelled;
         rental_transaction.completed_at = Some(current_time);
 
-        emit!(RentalCancelledByOwner {
+        ctx.accounts.renter_profile.record_cancellation()?;
+
+        emit!(RentalBalanceDefaulted {
             booking_id: rental_transaction.booking_id.clone(),
-            owner: ctx.accounts.owner.key(),
-            refund_amount: total_amount,
+            amount_paid: rental_transaction.amount_paid,
+            refund_amount: cancellation.refund_amount,
+            cancellation_fee: cancellation.cancellation_fee,
+            triggered_by: ctx.accounts.caller.key(),
             cancelled_at: current_time,
         });
 
         Ok(())
     }
-}
 
-#[derive(Accounts)]
-pub struct Initialize<'info> {
-    #[account(
-        init,
-        payer = admin,
-        space = 8 + 32 + 2 + 1,
-        seeds = [b"global_state"],
-        bump
-    )]
-    pub global_state: Account<'info, GlobalState>,
-    
-    #[account(mut)]
-    pub admin: Signer<'info>,
-    
-    pub system_program: Program<'info, System>,
-}
+    pub fn start_installments(ctx: Context<PayPartial>, installment_count: u8) -> Result<()> {
+        let rental_transaction = &mut ctx.accounts.rental_transaction;
+        let current_time = Clock::get()?.unix_timestamp;
+
+        require!(
+            rental_transaction.status == TransactionStatus::Created,
+            ErrorCode::InvalidTransactionStatus
+        );
+        require!(
+            (2..=MAX_INSTALLMENTS).contains(&installment_count),
+            ErrorCode::InvalidInstallmentCount
+        );
+        require!(
+            rental_transaction.rental_end - rental_transaction.rental_start >= installment_count as i64 * SECONDS_PER_DAY,
+            ErrorCode::InvalidInstallmentCount
+        );
+        require!(
+            current_time < rental_transaction.rental_start,
+            ErrorCode::InstallmentOverdue
+        );
+
+        rental_transaction.installment_count = installment_count;
+        let first_installment = rental_transaction.installment_amount(0)?;
+
+        // The full service fee is collected with the first installment
+        let fee_clamp = load_config::<FeeClampConfig>(&ctx.accounts.fee_clamp)?;
+        let service_fee = calculate_service_fee(
+            rental_transaction.total_amount,
+            &ctx.accounts.global_state,
+            fee_clamp.as_ref(),
+        )?;
+        let amount_due = first_installment
+            .checked_add(service_fee)
+            .ok_or(ErrorCode::MathOverflow)?;
+
+        transfer_to_escrow(
+            &ctx.accounts.renter_token_account,
+            &ctx.accounts.escrow_token_account,
+            &ctx.accounts.renter,
+            &ctx.accounts.token_program,
+            amount_due,
+        )?;
+
+        rental_transaction.status = TransactionStatus::PartiallyPaid;
+        rental_transaction.escrow_bump = ctx.bumps.escrow_token_account;
+        rental_transaction.service_fee = service_fee;
+        rental_transaction.amount_paid = first_installment;
+        rental_transaction.installments_paid = 1;
+
+        emit!(InstallmentPaid {
+            booking_id: rental_transaction.booking_id.clone(),
+            installment_index: 0,
+            amount: first_installment,
+            service_fee,
+            released_to_owner: 0,
+            next_due_at: Some(rental_transaction.installment_due_at(1)?),
+            renter: ctx.accounts.renter.key(),
+        });
+
+        Ok(())
+    }
+
+    pub fn pay_installment<'info>(
+        ctx: Context<'_, '_, 'info, 'info, PayInstallment<'info>>,
+    ) -> Result<()> {
+        let current_time = Clock::get()?.unix_timestamp;
+        let rental = &ctx.accounts.rental_transaction;
+
+        require!(
+            ctx.accounts.renter.key() == rental.renter,
+            ErrorCode::UnauthorizedCancellation
+        );
+        require!(
+            rental.status == TransactionStatus::PartiallyPaid && rental.installment_count > 0,
+            ErrorCode::InvalidTransactionStatus
+        );
+
+        let index = rental.installments_paid;
+        require!(index < rental.installment_count, ErrorCode::InvalidTransactionStatus);
+
+        // An installment can be paid once the period it follows has begun
+        let previous_period_start = rental.installment_due_at(index - 1)?;
+        let due_at = rental.installment_due_at(index)?;
+        require!(current_time >= previous_period_start, ErrorCode::InstallmentNotYetDue);
+        require!(
+            current_time <= due_at
+                .checked_add(ctx.accounts.global_state.installment_grace_period)
+                .ok_or(ErrorCode::MathOverflow)?,
+            ErrorCode::InstallmentOverdue
+        );
+
+        let amount = rental.installment_amount(index)?;
+        // Only periods that have already ended are released to the owner, once the
+        // handover is confirmed, and owner refunds come out of those earnings
+        let releasable_amount = if rental.is_handover_confirmed(
+            current_time,
+            ctx.accounts.global_state.no_show_contest_window,
+        )? {
+            rental.ended_installments_total(current_time)?
+                .saturating_sub(rental.released_amount)
+                .saturating_sub(rental.refunded_amount)
+        } else {
+            0
+        };
+
+        transfer_to_escrow(
+            &ctx.accounts.renter_token_account,
+            &ctx.accounts.escrow_token_account,
+            &ctx.accounts.renter,
+            &ctx.accounts.token_program,
+            amount,
+        )?;
+
+        // Paying an installment releases the earnings of every period that has ended
+        let category_fee = load_category_fee(
+            &ctx.accounts.rental_transaction,
+            ctx.accounts.category_fee.as_ref(),
+        )?;
+        let fee_tiers = load_config::<FeeTierConfig>(&ctx.accounts.fee_tiers)?;
+        let platform_fee_rate = resolve_platform_fee_rate(
+            &ctx.accounts.global_state,
+            category_fee.as_ref(),
+            fee_tiers.as_ref(),
+            ctx.accounts.owner_profile.owner_volume,
+        );
+        let fee_clamp = load_config::<FeeClampConfig>(&ctx.accounts.fee_clamp)?;
+        let (owner_amount, platform_fee) = if releasable_amount > 0 {
+            release_owner_earnings(
+                &ctx.accounts.rental_transaction,
+                &ctx.accounts.escrow_token_account,
+                &ctx.accounts.owner_token_account,
+                &ctx.accounts.treasury_token_account,
+                ctx.accounts.referrer_account.as_mut(),
+                ctx.accounts.referrer_token_account.as_ref(),
+                ctx.remaining_accounts,
+                &ctx.accounts.token_program,
+                releasable_amount,
+                platform_fee_rate,
+                ctx.accounts.global_state.referral_fee_share,
+                fee_clamp.as_ref(),
+            )?
+        } else {
+            (0, 0)
+        };
+
+        let rental_transaction = &mut ctx.accounts.rental_transaction;
+        rental_transaction.amount_paid = rental_transaction.amount_paid
+            .checked_add(amount)
+            .ok_or(ErrorCode::MathOverflow)?;
+        rental_transaction.released_amount = rental_transaction.released_amount
+            .checked_add(releasable_amount)
+            .ok_or(ErrorCode::MathOverflow)?;
+        rental_transaction.released_fee = rental_transaction.released_fee
+            .checked_add(platform_fee)
+            .ok_or(ErrorCode::MathOverflow)?;
+        rental_transaction.installments_paid = index + 1;
+
+        let next_due_at = if rental_transaction.installments_paid == rental_transaction.installment_count {
+            rental_transaction.status = TransactionStatus::Paid;
+            rental_transaction.paid_at = Some(current_time);
+            None
+        } else {
+            Some(rental_transaction.installment_due_at(index + 1)?)
+        };
+
+        emit!(InstallmentPaid {
+            booking_id: rental_transaction.booking_id.clone(),
+            installment_index: index,
+            amount,
+            service_fee: 0,
+            released_to_owner: owner_amount,
+            next_due_at,
+            renter: ctx.accounts.renter.key(),
+        });
+
+        Ok(())
+    }
+
+    pub fn default_installment_plan<'info>(
+        ctx: Context<'_, '_, 'info, 'info, DefaultInstallmentPlan<'info>>,
+    ) -> Result<()> {
+        let current_time = Clock::get()?.unix_timestamp;
+        let rental = &ctx.accounts.rental_transaction;
+
+        require!(
+            ctx.accounts.owner.key() == rental.owner_wallet,
+            ErrorCode::UnauthorizedOwnerCancellation
+        );
+        require!(
+            rental.status == TransactionStatus::PartiallyPaid && rental.installment_count > 0,
+            ErrorCode::InvalidTransactionStatus
+        );
+
+        let missed_index = rental.installments_paid;
+        let default_at = rental.installment_due_at(missed_index)?
+            .checked_add(ctx.accounts.global_state.installment_grace_period)
+            .ok_or(ErrorCode::MathOverflow)?;
+        require!(current_time > default_at, ErrorCode::InstallmentNotOverdue);
+
+        // The owner keeps everything paid so far; the service fee stays with the platform
+        let accrued_amount = rental.escrowed_amount()?;
+        let service_fee = rental.service_fee;
+
+        let category_fee = load_category_fee(
+            &ctx.accounts.rental_transaction,
+            ctx.accounts.category_fee.as_ref(),
+        )?;
+        let fee_tiers = load_config::<FeeTierConfig>(&ctx.accounts.fee_tiers)?;
+        let platform_fee_rate = resolve_platform_fee_rate(
+            &ctx.accounts.global_state,
+            category_fee.as_ref(),
+            fee_tiers.as_ref(),
+            ctx.accounts.owner_profile.owner_volume,
+        );
+        let fee_clamp = load_config::<FeeClampConfig>(&ctx.accounts.fee_clamp)?;
+        let (owner_amount, platform_fee) = release_owner_earnings(
+            &ctx.accounts.rental_transaction,
+            &ctx.accounts.escrow_token_account,
+            &ctx.accounts.owner_token_account,
+            &ctx.accounts.treasury_token_account,
+            ctx.accounts.referrer_account.as_mut(),
+            ctx.accounts.referrer_token_account.as_ref(),
+            ctx.remaining_accounts,
+            &ctx.accounts.token_program,
+            accrued_amount,
+            platform_fee_rate,
+            ctx.accounts.global_state.referral_fee_share,
+            fee_clamp.as_ref(),
+        )?;
+
+        transfer_from_escrow(
+            &ctx.accounts.rental_transaction,
+            &ctx.accounts.escrow_token_account,
+            ctx.accounts.treasury_token_account.to_account_info(),
+            &ctx.accounts.token_program,
+            service_fee,
+        )?;
+
+        let rental_transaction = &mut ctx.accounts.rental_transaction;
+        rental_transaction.released_amount = rental_transaction.released_amount
+            .checked_add(accrued_amount)
+            .ok_or(ErrorCode::MathOverflow)?;
+        rental_transaction.released_fee = rental_transaction.released_fee
+            .checked_add(platform_fee)
+            .ok_or(ErrorCode::MathOverflow)?;
+        rental_transaction.status = TransactionStatus::Cancelled;
+        rental_transaction.completed_at = Some(current_time);
+
+        ctx.accounts.renter_profile.record_cancellation()?;
+
+        emit!(InstallmentPlanDefaulted {
+            booking_id: rental_transaction.booking_id.clone(),
+            missed_installment: missed_index,
+            amount_paid: rental_transaction.amount_paid,
+            owner_amount,
+            platform_fee,
+            defaulted_at: current_time,
+        });
+
+        Ok(())
+    }
+
+    pub fn claim_accrued<'info>(
+        ctx: Context<'_, '_, 'info, 'info, ClaimAccrued<'info>>,
+    ) -> Result<()> {
+        let current_time = Clock::get()?.unix_timestamp;
+        let rental = &ctx.accounts.rental_transaction;
+
+        require!(
+            ctx.accounts.owner.key() == rental.owner_wallet,
+            ErrorCode::UnauthorizedClaim
+        );
+        require!(
+            rental.status == TransactionStatus::Paid,
+            ErrorCode::InvalidTransactionStatus
+        );
+        require!(
+            rental.is_handover_confirmed(
+                current_time,
+                ctx.accounts.global_state.no_show_contest_window,
+            )?,
+            ErrorCode::HandoverNotConfirmed
+        );
+
+        let claimable = rental.accrued_amount(current_time)?
+            .checked_sub(rental.released_amount)
+            .ok_or(ErrorCode::NothingToClaim)?;
+        require!(claimable > 0, ErrorCode::NothingToClaim);
+
+        let category_fee = load_category_fee(
+            &ctx.accounts.rental_transaction,
+            ctx.accounts.category_fee.as_ref(),
+        )?;
+        let fee_tiers = load_config::<FeeTierConfig>(&ctx.accounts.fee_tiers)?;
+        let platform_fee_rate = resolve_platform_fee_rate(
+            &ctx.accounts.global_state,
+            category_fee.as_ref(),
+            fee_tiers.as_ref(),
+            ctx.accounts.owner_profile.owner_volume,
+        );
+        let fee_clamp = load_config::<FeeClampConfig>(&ctx.accounts.fee_clamp)?;
+        let (owner_amount, platform_fee) = release_owner_earnings(
+            &ctx.accounts.rental_transaction,
+            &ctx.accounts.escrow_token_account,
+            &ctx.accounts.owner_token_account,
+            &ctx.accounts.treasury_token_account,
+            ctx.accounts.referrer_account.as_mut(),
+            ctx.accounts.referrer_token_account.as_ref(),
+            ctx.remaining_accounts,
+            &ctx.accounts.token_program,
+            claimable,
+            platform_fee_rate,
+            ctx.accounts.global_state.referral_fee_share,
+            fee_clamp.as_ref(),
+        )?;
+
+        let rental_transaction = &mut ctx.accounts.rental_transaction;
+        rental_transaction.released_amount = rental_transaction.released_amount
+            .checked_add(claimable)
+            .ok_or(ErrorCode::MathOverflow)?;
+        rental_transaction.released_fee = rental_transaction.released_fee
+            .checked_add(platform_fee)
+            .ok_or(ErrorCode::MathOverflow)?;
+
+        emit!(AccruedEarningsClaimed {
+            booking_id: rental_transaction.booking_id.clone(),
+            amount: claimable,
+            owner_amount,
+            platform_fee,
+            released_amount: rental_transaction.released_amount,
+            claimed_at: current_time,
+        });
+
+        Ok(())
+    }
+
+    pub fn complete_rental<'info>(
+        ctx: Context<'_, '_, 'info, 'info, CompleteRental<'info>>,
+    ) -> Result<()> {
+        let current_time = Clock::get()?.unix_timestamp;
+        let signer = ctx.accounts.signer.key();
+
+        require!(
+            ctx.accounts.rental_transaction.status == TransactionStatus::Paid,
+            ErrorCode::InvalidTransactionStatus
+        );
+
+        require!(
+            signer == ctx.accounts.rental_transaction.renter ||
+            signer == ctx.accounts.rental_transaction.owner_wallet ||
+            signer == ctx.accounts.global_state.admin,
+            ErrorCode::UnauthorizedCompletion
+        );
+
+        let grace_period = 24 * 60 * 60;
+        let completion_allowed_time = ctx.accounts.rental_transaction.rental_end + grace_period;
+        
+        require!(
+            current_time >= completion_allowed_time || 
+            signer == ctx.accounts.rental_transaction.renter,
+            ErrorCode::CompletionNotAllowed
+        );
+
+        settle_rental(ctx.accounts, ctx.remaining_accounts, 0, signer, current_time)
+    }
+
+    pub fn mutual_settle<'info>(
+        ctx: Context<'_, '_, 'info, 'info, MutualSettle<'info>>,
+        renter_refund_percentage: u16,
+    ) -> Result<()> {
+        let current_time = Clock::get()?.unix_timestamp;
+        let rental = &ctx.accounts.rental_transaction;
+
+        require!(
+            ctx.accounts.owner.key() == rental.owner_wallet &&
+            ctx.accounts.renter.key() == rental.renter,
+            ErrorCode::UnauthorizedSettlement
+        );
+        require!(
+            rental.status == TransactionStatus::Paid ||
+            rental.status == TransactionStatus::PartiallyPaid ||
+            rental.status == TransactionStatus::Disputed,
+            ErrorCode::InvalidTransactionStatus
+        );
+        require!(renter_refund_percentage <= 10000, ErrorCode::InvalidPercentages);
+
+        let Settlement {
+            renter_refund,
+            owner_share,
+            service_fee_refund,
+            service_fee_retained,
+        } = rental.settlement(renter_refund_percentage)?;
+
+        let platform_fee = match ctx.accounts.global_state.settlement_fee_mode {
+            SettlementFeeMode::Waived => 0,
+            SettlementFeeMode::OwnerShare => {
+                let category_fee = load_category_fee(rental, ctx.accounts.category_fee.as_ref())?;
+                let fee_tiers = load_config::<FeeTierConfig>(&ctx.accounts.fee_tiers)?;
+                let platform_fee_rate = resolve_platform_fee_rate(
+                    &ctx.accounts.global_state,
+                    category_fee.as_ref(),
+                    fee_tiers.as_ref(),
+                    ctx.accounts.owner_profile.owner_volume,
+                );
+                let fee_clamp = load_config::<FeeClampConfig>(&ctx.accounts.fee_clamp)?;
+                rental.release_platform_fee(owner_share, platform_fee_rate, fee_clamp.as_ref())?
+            }
+        };
+        let owner_amount = owner_share
+            .checked_sub(platform_fee)
+            .ok_or(ErrorCode::MathOverflow)?;
+
+        let (renter_refund_share, subsidy_refund) = rental.split_refund(renter_refund)?;
+        let renter_payout = renter_refund_share
+            .checked_add(service_fee_refund)
+            .ok_or(ErrorCode::MathOverflow)?;
+        let treasury_payout = platform_fee
+            .checked_add(service_fee_retained)
+            .ok_or(ErrorCode::MathOverflow)?
+            .checked_add(subsidy_refund)
+            .ok_or(ErrorCode::MathOverflow)?;
+
+        let mut payouts = Vec::new();
+
+        distribute_owner_proceeds(
+            &ctx.accounts.rental_transaction,
+            &ctx.accounts.escrow_token_account,
+            &ctx.accounts.owner_token_account,
+            ctx.remaining_accounts,
+            &ctx.accounts.token_program,
+            owner_amount,
+            &mut payouts,
+        )?;
+
+        transfer_from_escrow(
+            &ctx.accounts.rental_transaction,
+            &ctx.accounts.escrow_token_account,
+            ctx.accounts.renter_token_account.to_account_info(),
+            &ctx.accounts.token_program,
+            renter_payout,
+        )?;
+        payouts.push(PayoutRecipient {
+            recipient: ctx.accounts.rental_transaction.renter,
+            amount: renter_payout,
+            kind: PayoutKind::Renter,
+        });
+
+        transfer_from_escrow(
+            &ctx.accounts.rental_transaction,
+            &ctx.accounts.escrow_token_account,
+            ctx.accounts.treasury_token_account.to_account_info(),
+            &ctx.accounts.token_program,
+            treasury_payout,
+        )?;
+        payouts.push(PayoutRecipient {
+            recipient: ctx.accounts.treasury_token_account.key(),
+            amount: treasury_payout,
+            kind: PayoutKind::Platform,
+        });
+
+        let rental_transaction = &mut ctx.accounts.rental_transaction;
+        rental_transaction.status = TransactionStatus::Settled;
+        rental_transaction.completed_at = Some(current_time);
+
+        emit!(RentalSettledByAgreement {
+            booking_id: rental_transaction.booking_id.clone(),
+            renter_refund,
+            owner_amount,
+            platform_fee,
+            service_fee_refund,
+            settled_at: current_time,
+        });
+
+        emit!(PayoutDistributed {
+            booking_id: rental_transaction.booking_id.clone(),
+            payouts,
+        });
+
+        Ok(())
+    }
+
+    pub fn owner_refund(ctx: Context<OwnerRefund>, amount: u64) -> Result<()> {
+        let current_time = Clock::get()?.unix_timestamp;
+        let rental = &ctx.accounts.rental_transaction;
+
+        require!(
+            ctx.accounts.owner.key() == rental.owner_wallet,
+            ErrorCode::UnauthorizedOwnerRefund
+        );
+        require!(amount > 0, ErrorCode::InvalidAmount);
+
+        let from_escrow = match rental.status {
+            TransactionStatus::Paid |
+            TransactionStatus::PartiallyPaid |
+            TransactionStatus::Disputed => true,
+            TransactionStatus::Completed |
+            TransactionStatus::Resolved |
+            TransactionStatus::Settled => false,
+            _ => return err!(ErrorCode::InvalidTransactionStatus),
+        };
+
+        let refunded_amount = rental.refunded_amount
+            .checked_add(amount)
+            .ok_or(ErrorCode::MathOverflow)?;
+
+        if from_escrow {
+            // While funds are in escrow the refund comes out of the owner's share
+            require!(amount <= rental.escrowed_amount()?, ErrorCode::RefundExceedsAvailable);
+
+            transfer_from_escrow(
+                &ctx.accounts.rental_transaction,
+                &ctx.accounts.escrow_token_account,
+                ctx.accounts.renter_token_account.to_account_info(),
+                &ctx.accounts.token_program,
+                amount,
+            )?;
+        } else {
+            // After payout the owner refunds from their own token account
+            require!(refunded_amount <= rental.total_amount, ErrorCode::RefundExceedsAvailable);
+
+            let transfer_accounts = Transfer {
+                from: ctx.accounts.owner_token_account.to_account_info(),
+                to: ctx.accounts.renter_token_account.to_account_info(),
+                authority: ctx.accounts.owner.to_account_info(),
+            };
+            let cpi_ctx = CpiContext::new(
+                ctx.accounts.token_program.to_account_info(),
+                transfer_accounts,
+            );
+            token::transfer(cpi_ctx, amount)?;
+        }
+
+        let rental_transaction = &mut ctx.accounts.rental_transaction;
+        rental_transaction.refunded_amount = refunded_amount;
+
+        emit!(OwnerRefundIssued {
+            booking_id: rental_transaction.booking_id.clone(),
+            owner: rental_transaction.owner_wallet,
+            renter: rental_transaction.renter,
+            amount,
+            refunded_amount,
+            from_escrow,
+            refunded_at: current_time,
+        });
+
+        Ok(())
+    }
+
+    pub fn propose_amendment(
+        ctx: Context<ProposeAmendment>,
+        total_amount: u64,
+        rental_start: i64,
+        rental_end: i64,
+    ) -> Result<()> {
+        let rental = &ctx.accounts.rental_transaction;
+        let current_time = Clock::get()?.unix_timestamp;
+
+        require!(
+            ctx.accounts.owner.key() == rental.owner_wallet,
+            ErrorCode::UnauthorizedAmendment
+        );
+        require!(
+            rental.status == TransactionStatus::Created,
+            ErrorCode::InvalidTransactionStatus
+        );
+        RentalTransaction::validate_booking(
+            rental.renter,
+            rental.owner_wallet,
+            total_amount,
+            rental_start,
+            rental_end,
+            &rental.booking_id,
+        )?;
+        require!(rental_start > current_time, ErrorCode::InvalidRentalPeriod);
+
+        let amendment_number = rental.amendment_count
+            .checked_add(1)
+            .ok_or(ErrorCode::MathOverflow)?;
+
+        let amendment = &mut ctx.accounts.amendment;
+        amendment.rental_transaction = rental.key();
+        amendment.owner = rental.owner_wallet;
+        amendment.amendment_number = amendment_number;
+        amendment.total_amount = total_amount;
+        amendment.rental_start = rental_start;
+        amendment.rental_end = rental_end;
+        amendment.proposed_at = current_time;
+        amendment.bump = ctx.bumps.amendment;
+
+        emit!(AmendmentProposed {
+            booking_id: rental.booking_id.clone(),
+            amendment_number,
+            total_amount,
+            rental_start,
+            rental_end,
+            proposed_at: current_time,
+        });
+
+        Ok(())
+    }
+
+    // The renter restates the terms they are accepting, so a proposal swapped in
+    // after they reviewed it cannot be accepted by mistake
+    pub fn accept_amendment(
+        ctx: Context<AcceptAmendment>,
+        total_amount: u64,
+        rental_start: i64,
+        rental_end: i64,
+        quote_expires_at: Option<i64>,
+    ) -> Result<()> {
+        let amendment = &ctx.accounts.amendment;
+        let current_time = Clock::get()?.unix_timestamp;
+
+        require!(
+            ctx.accounts.renter.key() == ctx.accounts.rental_transaction.renter,
+            ErrorCode::UnauthorizedAmendment
+        );
+        require!(
+            ctx.accounts.rental_transaction.status == TransactionStatus::Created,
+            ErrorCode::InvalidTransactionStatus
+        );
+        require!(
+            amendment.matches(&ctx.accounts.rental_transaction, total_amount, rental_start, rental_end),
+            ErrorCode::AmendmentMismatch
+        );
+        require!(rental_start > current_time, ErrorCode::InvalidRentalPeriod);
+
+        // Amended terms are validated and priced the same way as a new booking
+        let rental = &ctx.accounts.rental_transaction;
+        RentalTransaction::validate_booking(
+            rental.renter,
+            rental.owner_wallet,
+            total_amount,
+            rental_start,
+            rental_end,
+            &rental.booking_id,
+        )?;
+        let product = load_config::<Product>(&ctx.accounts.product)?;
+        verify_booking_price(
+            product.as_ref(),
+            &ctx.accounts.global_state,
+            &ctx.accounts.instructions_sysvar,
+            rental.product_id,
+            rental.owner_wallet,
+            total_amount,
+            rental_start,
+            rental_end,
+            rental.category_id,
+            quote_expires_at,
+        )?;
+
+        let rental_transaction = &mut ctx.accounts.rental_transaction;
+        let old_total_amount = rental_transaction.total_amount;
+        let old_start = rental_transaction.rental_start;
+        let old_end = rental_transaction.rental_end;
+
+        rental_transaction.total_amount = total_amount;
+        rental_transaction.rental_start = rental_start;
+        rental_transaction.rental_end = rental_end;
+        rental_transaction.amendment_count = amendment.amendment_number;
+        ctx.accounts.owner_profile.lock_bond_until(rental_start);
+
+        emit!(BookingAmended {
+            booking_id: rental_transaction.booking_id.clone(),
+            amendment_number: rental_transaction.amendment_count,
+            old_total_amount,
+            new_total_amount: total_amount,
+            old_start,
+            old_end,
+            new_start: rental_start,
+            new_end: rental_end,
+        });
+
+        Ok(())
+    }
+
+    pub fn cancel_amendment(ctx: Context<CancelAmendment>) -> Result<()> {
+        let amendment = &ctx.accounts.amendment;
+        let signer = ctx.accounts.signer.key();
+
+        // Only the parties can withdraw an amendment to an unpaid booking; once the
+        // booking is paid or closed, anyone can clear it
+        let rental = load_config::<RentalTransaction>(&ctx.accounts.rental_transaction)?;
+        if let Some(rental) = rental {
+            if rental.status == TransactionStatus::Created {
+                require!(
+                    signer == rental.renter || signer == rental.owner_wallet,
+                    ErrorCode::UnauthorizedAmendment
+                );
+            }
+        }
+
+        emit!(AmendmentCancelled {
+            rental_transaction: amendment.rental_transaction,
+            amendment_number: amendment.amendment_number,
+            cancelled_by: signer,
+        });
+
+        Ok(())
+    }
+
+    pub fn propose_reschedule(
+        ctx: Context<ProposeReschedule>,
+        rental_start: i64,
+        rental_end: i64,
+        total_amount: u64,
+    ) -> Result<()> {
+        let rental = &ctx.accounts.rental_transaction;
+        let proposer = ctx.accounts.proposer.key();
+        let current_time = Clock::get()?.unix_timestamp;
+
+        require!(
+            proposer == rental.renter || proposer == rental.owner_wallet,
+            ErrorCode::UnauthorizedReschedule
+        );
+        rental.require_reschedulable(current_time)?;
+        RentalTransaction::validate_booking(
+            rental.renter,
+            rental.owner_wallet,
+            total_amount,
+            rental_start,
+            rental_end,
+            &rental.booking_id,
+        )?;
+        require!(rental_start > current_time, ErrorCode::InvalidRentalPeriod);
+
+        let fee_clamp = load_config::<FeeClampConfig>(&ctx.accounts.fee_clamp)?;
+        let service_fee = calculate_service_fee(
+            total_amount,
+            &ctx.accounts.global_state,
+            fee_clamp.as_ref(),
+        )?;
+
+        // A renter asking for a pricier slot pays the difference up front so the
+        // owner can accept without the renter signing again
+        let new_amount_due = total_amount
+            .checked_add(service_fee)
+            .ok_or(ErrorCode::MathOverflow)?;
+        let old_amount_paid = rental.total_amount
+            .checked_add(rental.service_fee)
+            .ok_or(ErrorCode::MathOverflow)?;
+        let prefunded = if proposer == rental.renter {
+            new_amount_due.saturating_sub(old_amount_paid)
+        } else {
+            0
+        };
+
+        if prefunded > 0 {
+            let renter_token_account = ctx.accounts.renter_token_account
+                .as_ref()
+                .ok_or(ErrorCode::InvalidRenterTokenAccount)?;
+            transfer_to_escrow(
+                renter_token_account,
+                &ctx.accounts.reschedule_escrow_token_account,
+                &ctx.accounts.proposer,
+                &ctx.accounts.token_program,
+                prefunded,
+            )?;
+        }
+
+        let proposal = &mut ctx.accounts.reschedule_proposal;
+        proposal.rental_transaction = rental.key();
+        proposal.proposer = proposer;
+        proposal.renter = rental.renter;
+        proposal.rental_start = rental_start;
+        proposal.rental_end = rental_end;
+        proposal.total_amount = total_amount;
+        proposal.service_fee = service_fee;
+        proposal.prefunded = prefunded;
+        proposal.proposed_at = current_time;
+        // A proposal can only be accepted before either the current or the new dates begin
+        proposal.expires_at = rental.rental_start.min(rental_start);
+        proposal.bump = ctx.bumps.reschedule_proposal;
+
+        emit!(RescheduleProposed {
+            booking_id: rental.booking_id.clone(),
+            proposer,
+            rental_start,
+            rental_end,
+            total_amount,
+            proposed_at: current_time,
+        });
+
+        Ok(())
+    }
+
+    pub fn accept_reschedule(
+        ctx: Context<AcceptReschedule>,
+        quote_expires_at: Option<i64>,
+    ) -> Result<()> {
+        let rental = &ctx.accounts.rental_transaction;
+        let proposal = &ctx.accounts.reschedule_proposal;
+        let acceptor = ctx.accounts.acceptor.key();
+        let current_time = Clock::get()?.unix_timestamp;
+
+        require!(
+            (acceptor == rental.renter || acceptor == rental.owner_wallet) &&
+            acceptor != proposal.proposer,
+            ErrorCode::UnauthorizedReschedule
+        );
+        rental.require_reschedulable(current_time)?;
+        require!(current_time < proposal.expires_at, ErrorCode::RescheduleExpired);
+
+        // The new dates must be priced like a fresh booking. Availability is only known
+        // to the backend, which signs quotes just for open dates, so a signed quote is
+        // required even when the product prices the new dates on-chain.
+        let global_state = &ctx.accounts.global_state;
+        require!(
+            global_state.quote_signer != Pubkey::default(),
+            ErrorCode::MissingPriceQuote
+        );
+        if let Some(product) = load_config::<Product>(&ctx.accounts.product)? {
+            let quoted_price = product.rates.quote_price(proposal.rental_start, proposal.rental_end)?;
+            require!(proposal.total_amount == quoted_price, ErrorCode::PriceMismatch);
+        }
+        let quote = PriceQuote {
+            product_id: rental.product_id,
+            owner_wallet: rental.owner_wallet,
+            total_amount: proposal.total_amount,
+            rental_start: proposal.rental_start,
+            rental_end: proposal.rental_end,
+            category_id: rental.category_id,
+            expires_at: quote_expires_at.ok_or(ErrorCode::MissingPriceQuote)?,
+        };
+        verify_signed_quote(global_state, &ctx.accounts.instructions_sysvar, &quote)?;
+
+        // The renter's prefunded difference joins the rental's escrow
+        transfer_from_reschedule_escrow(
+            &ctx.accounts.reschedule_proposal,
+            &ctx.accounts.reschedule_escrow_token_account,
+            ctx.accounts.escrow_token_account.to_account_info(),
+            &ctx.accounts.token_program,
+            proposal.prefunded,
+        )?;
+
+        let old_amount_paid = rental.total_amount
+            .checked_add(rental.service_fee)
+            .ok_or(ErrorCode::MathOverflow)?;
+        let held_amount = old_amount_paid
+            .checked_add(proposal.prefunded)
+            .ok_or(ErrorCode::MathOverflow)?;
+        let new_amount_due = proposal.total_amount
+            .checked_add(proposal.service_fee)
+            .ok_or(ErrorCode::MathOverflow)?;
+
+        if new_amount_due > held_amount {
+            // Only reachable when the renter accepts an owner's proposal
+            transfer_to_escrow(
+                &ctx.accounts.renter_token_account,
+                &ctx.accounts.escrow_token_account,
+                &ctx.accounts.acceptor,
+                &ctx.accounts.token_program,
+                new_amount_due - held_amount,
+            )?;
+        } else {
+            transfer_from_escrow(
+                &ctx.accounts.rental_transaction,
+                &ctx.accounts.escrow_token_account,
+                ctx.accounts.renter_token_account.to_account_info(),
+                &ctx.accounts.token_program,
+                held_amount - new_amount_due,
+            )?;
+        }
+
+        let proposal = &ctx.accounts.reschedule_proposal;
+        let rental_transaction = &mut ctx.accounts.rental_transaction;
+        let old_start = rental_transaction.rental_start;
+        let old_end = rental_transaction.rental_end;
+        let old_total_amount = rental_transaction.total_amount;
+
+        rental_transaction.rental_start = proposal.rental_start;
+        rental_transaction.rental_end = proposal.rental_end;
+        rental_transaction.total_amount = proposal.total_amount;
+        rental_transaction.amount_paid = proposal.total_amount;
+        rental_transaction.service_fee = proposal.service_fee;
+        ctx.accounts.owner_profile.lock_bond_until(proposal.rental_start);
+
+        emit!(RentalRescheduled {
+            booking_id: rental_transaction.booking_id.clone(),
+            old_start,
+            old_end,
+            new_start: rental_transaction.rental_start,
+            new_end: rental_transaction.rental_end,
+            old_total_amount,
+            new_total_amount: rental_transaction.total_amount,
+            proposed_by: proposal.proposer,
+            accepted_by: acceptor,
+        });
+
+        Ok(())
+    }
+
+    pub fn cancel_reschedule(ctx: Context<CancelReschedule>) -> Result<()> {
+        let proposal = &ctx.accounts.reschedule_proposal;
+        let signer = ctx.accounts.signer.key();
+        let current_time = Clock::get()?.unix_timestamp;
+
+        // Only the parties can withdraw a proposal that can still be accepted; once it
+        // has expired or the rental has moved on, anyone can clear it
+        let rental = load_config::<RentalTransaction>(&ctx.accounts.rental_transaction)?;
+        if let Some(rental) = rental {
+            if rental.status == TransactionStatus::Paid && current_time < proposal.expires_at {
+                require!(
+                    signer == rental.renter || signer == rental.owner_wallet,
+                    ErrorCode::UnauthorizedReschedule
+                );
+            }
+        }
+
+        // Return anything the renter put up front for the proposal
+        transfer_from_reschedule_escrow(
+            proposal,
+            &ctx.accounts.reschedule_escrow_token_account,
+            ctx.accounts.renter_token_account.to_account_info(),
+            &ctx.accounts.token_program,
+            proposal.prefunded,
+        )?;
+
+        emit!(RescheduleCancelled {
+            rental_transaction: proposal.rental_transaction,
+            proposer: proposal.proposer,
+            cancelled_by: signer,
+        });
+
+        Ok(())
+    }
+
+    pub fn request_early_return(ctx: Context<RequestEarlyReturn>) -> Result<()> {
+        let rental_transaction = &mut ctx.accounts.rental_transaction;
+        let current_time = Clock::get()?.unix_timestamp;
+
+        require!(
+            ctx.accounts.renter.key() == rental_transaction.renter,
+            ErrorCode::UnauthorizedEarlyReturn
+        );
+        require!(
+            rental_transaction.status == TransactionStatus::Paid,
+            ErrorCode::InvalidTransactionStatus
+        );
+        require!(
+            current_time >= rental_transaction.rental_start &&
+            current_time < rental_transaction.rental_end,
+            ErrorCode::EarlyReturnNotAllowed
+        );
+        require!(
+            rental_transaction.early_return_requested_at.is_none(),
+            ErrorCode::EarlyReturnAlreadyRequested
+        );
+
+        rental_transaction.early_return_requested_at = Some(current_time);
+
+        emit!(EarlyReturnRequested {
+            booking_id: rental_transaction.booking_id.clone(),
+            renter: rental_transaction.renter,
+            requested_at: current_time,
+        });
+
+        Ok(())
+    }
+
+    pub fn confirm_early_return<'info>(
+        ctx: Context<'_, '_, 'info, 'info, CompleteRental<'info>>,
+    ) -> Result<()> {
+        let current_time = Clock::get()?.unix_timestamp;
+        let signer = ctx.accounts.signer.key();
+        let rental = &ctx.accounts.rental_transaction;
+
+        require!(
+            signer == rental.owner_wallet,
+            ErrorCode::UnauthorizedEarlyReturn
+        );
+        require!(
+            rental.status == TransactionStatus::Paid,
+            ErrorCode::InvalidTransactionStatus
+        );
+        let returned_at = rental.early_return_requested_at
+            .ok_or(ErrorCode::EarlyReturnNotRequested)?;
+
+        // Unused days are refunded according to the policy captured at booking time,
+        // limited to what is still held in escrow
+        let refund_amount = rental.policies.early_return
+            .refund_for(rental, returned_at)?
+            .min(rental.escrowed_amount()?);
+        let (renter_refund_share, subsidy_refund) = rental.split_refund(refund_amount)?;
+
+        let renter_token_account = ctx.accounts.renter_token_account
+            .as_ref()
+            .ok_or(ErrorCode::InvalidRenterTokenAccount)?;
+        transfer_from_escrow(
+            &ctx.accounts.rental_transaction,
+            &ctx.accounts.escrow_token_account,
+            renter_token_account.to_account_info(),
+            &ctx.accounts.token_program,
+            renter_refund_share,
+        )?;
+        transfer_from_escrow(
+            &ctx.accounts.rental_transaction,
+            &ctx.accounts.escrow_token_account,
+            ctx.accounts.treasury_token_account.to_account_info(),
+            &ctx.accounts.token_program,
+            subsidy_refund,
+        )?;
+
+        emit!(EarlyReturnConfirmed {
+            booking_id: ctx.accounts.rental_transaction.booking_id.clone(),
+            returned_at,
+            refund_amount,
+            confirmed_by: signer,
+        });
+
+        settle_rental(ctx.accounts, ctx.remaining_accounts, refund_amount, signer, current_time)
+    }
+
+    pub fn admin_intervene<'info>(
+        ctx: Context<'_, '_, 'info, 'info, AdminIntervene<'info>>,
+        owner_percentage: u16,
+        renter_refund_percentage: u16,
+        reason: String,
+    ) -> Result<()> {
+        require!(
+            ctx.accounts.admin.key() == ctx.accounts.global_state.admin,
+            ErrorCode::UnauthorizedAdmin
+        );
+        let total_percentage = owner_percentage
+            .checked_add(renter_refund_percentage)
+            .ok_or(ErrorCode::MathOverflow)?;
+        require!(
+            total_percentage <= 10000,
+            ErrorCode::InvalidPercentages
+        );
+        require!(reason.len() <= 256, ErrorCode::ReasonTooLong);
+
+        require!(
+            ctx.accounts.rental_transaction.is_disputable() ||
+            ctx.accounts.rental_transaction.status == TransactionStatus::Disputed,
+            ErrorCode::InvalidTransactionStatus
+        );
+
+        // Only earnings still held in escrow are subject to the ruling
+        let total_amount = ctx.accounts.rental_transaction.escrowed_amount()?;
+        
+        let owner_amount = total_amount
+            .checked_mul(owner_percentage as u64)
+            .ok_or(ErrorCode::MathOverflow)?
+            .checked_div(10000)
+            .ok_or(ErrorCode::MathOverflow)?;
+        
+        let renter_refund = total_amount
+            .checked_mul(renter_refund_percentage as u64)
+            .ok_or(ErrorCode::MathOverflow)?
+            .checked_div(10000)
+            .ok_or(ErrorCode::MathOverflow)?;
+        
+        let platform_fee = total_amount
+            .checked_sub(owner_amount)
+            .ok_or(ErrorCode::MathOverflow)?
+            .checked_sub(renter_refund)
+            .ok_or(ErrorCode::MathOverflow)?;
+
+        // Service fee is refunded in the same proportion as the rental price
+        let service_fee = ctx.accounts.rental_transaction.service_fee;
+        let service_fee_refund = service_fee
+            .checked_mul(renter_refund_percentage as u64)
+            .ok_or(ErrorCode::MathOverflow)?
+            .checked_div(10000)
+            .ok_or(ErrorCode::MathOverflow)?;
+        let service_fee_retained = service_fee
+            .checked_sub(service_fee_refund)
+            .ok_or(ErrorCode::MathOverflow)?;
+        let (renter_refund_share, subsidy_refund) = ctx.accounts.rental_transaction.split_refund(renter_refund)?;
+        let renter_payout = renter_refund_share
+            .checked_add(service_fee_refund)
+            .ok_or(ErrorCode::MathOverflow)?;
+        let treasury_payout = platform_fee
+            .checked_add(service_fee_retained)
+            .ok_or(ErrorCode::MathOverflow)?
+            .checked_add(subsidy_refund)
+            .ok_or(ErrorCode::MathOverflow)?;
+
+        let mut payouts = Vec::new();
+
+        distribute_owner_proceeds(
+            &ctx.accounts.rental_transaction,
+            &ctx.accounts.escrow_token_account,
+            &ctx.accounts.owner_token_account,
+            ctx.remaining_accounts,
+            &ctx.accounts.token_program,
+            owner_amount,
+            &mut payouts,
+        )?;
+
+        transfer_from_escrow(
+            &ctx.accounts.rental_transaction,
+            &ctx.accounts.escrow_token_account,
+            ctx.accounts.renter_token_account.to_account_info(),
+            &ctx.accounts.token_program,
+            renter_payout,
+        )?;
+        payouts.push(PayoutRecipient {
+            recipient: ctx.accounts.rental_transaction.renter,
+            amount: renter_payout,
+            kind: PayoutKind::Renter,
+        });
+
+        transfer_from_escrow(
+            &ctx.accounts.rental_transaction,
+            &ctx.accounts.escrow_token_account,
+            ctx.accounts.treasury_token_account.to_account_info(),
+            &ctx.accounts.token_program,
+            treasury_payout,
+        )?;
+        payouts.push(PayoutRecipient {
+            recipient: ctx.accounts.treasury_token_account.key(),
+            amount: treasury_payout,
+            kind: PayoutKind::Platform,
+        });
+
+        // When a dispute was opened, the party awarded the smaller share is recorded
+        // as having lost it
+        let disputed = ctx.accounts.rental_transaction.dispute_opened_by.is_some();
+        if disputed && owner_percentage < renter_refund_percentage {
+            let owner_profile = &mut ctx.accounts.owner_profile;
+            owner_profile.disputes_lost = owner_profile.disputes_lost
+                .checked_add(1)
+                .ok_or(ErrorCode::MathOverflow)?;
+        } else if disputed && renter_refund_percentage < owner_percentage {
+            let renter_profile = &mut ctx.accounts.renter_profile;
+            renter_profile.disputes_lost = renter_profile.disputes_lost
+                .checked_add(1)
+                .ok_or(ErrorCode::MathOverflow)?;
+        }
+
+        let rental_transaction = &mut ctx.accounts.rental_transaction;
+        rental_transaction.status = TransactionStatus::Resolved;
+        rental_transaction.completed_at = Some(Clock::get()?.unix_timestamp);
+        rental_transaction.resolution_reason = Some(reason.clone());
+
+        emit!(AdminIntervention {
+            booking_id: rental_transaction.booking_id.clone(),
+            owner_amount,
+            renter_refund,
+            platform_fee,
+            service_fee_refund,
+            reason,
+            admin: ctx.accounts.admin.key(),
+        });
+
+        emit!(PayoutDistributed {
+            booking_id: rental_transaction.booking_id.clone(),
+            payouts,
+        });
+
+        Ok(())
+    }
+
+    pub fn cancel_as_renter_created(ctx: Context<CancelAsRenterCreated>) -> Result<()> {
+        let rental_transaction = &mut ctx.accounts.rental_transaction;
+        let current_time = Clock::get()?.unix_timestamp;
+        
+        require!(
+            ctx.accounts.renter.key() == rental_transaction.renter,
+            ErrorCode::UnauthorizedCancellation
+        );
+        require!(
+            rental_transaction.status == TransactionStatus::Created,
+            ErrorCode::InvalidTransactionStatus
+        );
+
+        rental_transaction.status = TransactionStatus::Cancelled;
+        rental_transaction.completed_at = Some(current_time);
+
+        ctx.accounts.renter_profile.record_cancellation()?;
+
+        emit!(RentalCancelledByRenter {
+            booking_id: rental_transaction.booking_id.clone(),
+            renter: ctx.accounts.renter.key(),
+            cancelled_at: current_time,
+        });
+
+        Ok(())
+    }
+
+    pub fn cancel_as_renter_paid(ctx: Context<CancelAsRenterPaid>) -> Result<()> {
+        let rental_transaction = &mut ctx.accounts.rental_transaction;
+        let current_time = Clock::get()?.unix_timestamp;
+        
+        require!(
+            ctx.accounts.renter.key() == rental_transaction.renter,
+            ErrorCode::UnauthorizedCancellation
+        );
+        require!(
+            rental_transaction.status == TransactionStatus::Paid ||
+            rental_transaction.status == TransactionStatus::PartiallyPaid,
+            ErrorCode::InvalidTransactionStatus
+        );
+        require!(
+            current_time < rental_transaction.rental_start,
+            ErrorCode::CancellationTooLate
+        );
+
+        let fee_clamp = load_config::<FeeClampConfig>(&ctx.accounts.fee_clamp)?;
+        // Refund follows the cancellation policy captured at booking time
+        let cancellation = rental_transaction.renter_cancellation(
+            current_time,
+            fee_clamp.as_ref(),
+        )?;
+
+        transfer_from_escrow(
+            rental_transaction,
+            &ctx.accounts.escrow_token_account,
+            ctx.accounts.renter_token_account.to_account_info(),
+            &ctx.accounts.token_program,
+            cancellation.renter_payout,
+        )?;
+
+        transfer_from_escrow(
+            rental_transaction,
+            &ctx.accounts.escrow_token_account,
+            ctx.accounts.treasury_token_account.to_account_info(),
+            &ctx.accounts.token_program,
+            cancellation.treasury_payout,
+        )?;
+
+        rental_transaction.status = TransactionStatus::Cancelled;
+        rental_transaction.completed_at = Some(current_time);
+
+        ctx.accounts.renter_profile.record_cancellation()?;
+
+        emit!(RentalCancelledByRenterPaid {
+            booking_id: rental_transaction.booking_id.clone(),
+            renter: ctx.accounts.renter.key(),
+            refund_amount: cancellation.refund_amount,
+            cancellation_fee: cancellation.cancellation_fee,
+            service_fee_refund: cancellation.service_fee_refund,
+            cancelled_at: current_time,
+        });
+
+        Ok(())
+    }
+
+    pub fn cancel_as_owner(ctx: Context<CancelAsOwner>) -> Result<()> {
+        let current_time = Clock::get()?.unix_timestamp;
+        
+        require!(
+            ctx.accounts.rental_transaction.status == TransactionStatus::Paid ||
+            ctx.accounts.rental_transaction.status == TransactionStatus::PartiallyPaid,
+            ErrorCode::InvalidTransactionStatus
+        );
+        require!(
+            ctx.accounts.owner.key() == ctx.accounts.rental_transaction.owner_wallet,
+            ErrorCode::UnauthorizedOwnerCancellation
+        );
+        
+        let one_day_in_seconds = 24 * 60 * 60;
+        let cancellation_deadline = ctx.accounts.rental_transaction.rental_start
+            .checked_sub(one_day_in_seconds)
+            .ok_or(ErrorCode::MathOverflow)?;
+        
+        require!(
+            current_time <= cancellation_deadline,
+            ErrorCode::OwnerCancellationTooLate
+        );
+
+        // Late owner cancellations pay the renter a penalty out of the owner's bond.
+        // The bond must be supplied for them, since otherwise leaving it out would skip
+        // the penalty; an owner who never posted a bond cannot cancel inside the window.
+        let penalty = ctx.accounts.global_state
+            .owner_cancellation_penalty(&ctx.accounts.rental_transaction, current_time)?;
+        let penalty_paid = if penalty > 0 {
+            let owner_bond = ctx.accounts.owner_bond
+                .as_mut()
+                .ok_or(ErrorCode::OwnerBondRequired)?;
+            let bond_vault = ctx.accounts.bond_vault
+                .as_ref()
+                .ok_or(ErrorCode::OwnerBondRequired)?;
+            require_keys_eq!(owner_bond.mint, ctx.accounts.usdc_mint.key(), ErrorCode::InvalidBondMint);
+            pay_bond_penalty(
+                owner_bond,
+                bond_vault,
+                ctx.accounts.renter_token_account.to_account_info(),
+                &ctx.accounts.token_program,
+                penalty,
+            )?
+        } else {
+            0
+        };
+        
+        // Owner cancellations refund the rental price and the service fee in full;
+        // any platform-funded discount goes back to the treasury
+        let unreleased_amount = ctx.accounts.rental_transaction.escrowed_amount()?;
+        let service_fee = ctx.accounts.rental_transaction.service_fee;
+        let (renter_refund_share, subsidy_refund) = ctx.accounts.rental_transaction.split_refund(unreleased_amount)?;
+        let refund_amount = renter_refund_share
+            .checked_add(service_fee)
+            .ok_or(ErrorCode::MathOverflow)?;
+
+        transfer_from_escrow(
+            &ctx.accounts.rental_transaction,
+            &ctx.accounts.escrow_token_account,
+            ctx.accounts.renter_token_account.to_account_info(),
+            &ctx.accounts.token_program,
+            refund_amount,
+        )?;
+
+        transfer_from_escrow(
+            &ctx.accounts.rental_transaction,
+            &ctx.accounts.escrow_token_account,
+            ctx.accounts.treasury_token_account.to_account_info(),
+            &ctx.accounts.token_program,
+            subsidy_refund,
+        )?;
+        
+        let rental_transaction = &mut ctx.accounts.rental_transaction;
+        rental_transaction.status = TransactionStatus::Cancelled;
+        rental_transaction.completed_at = Some(current_time);
+
+        ctx.accounts.owner_profile.record_cancellation()?;
+
+        emit!(RentalCancelledByOwner {
+            booking_id: rental_transaction.booking_id.clone(),
+            owner: ctx.accounts.owner.key(),
+            refund_amount,
+            penalty: penalty_paid,
+            cancelled_at: current_time,
+        });
+
+        Ok(())
+    }
+
+    pub fn deposit_bond(ctx: Context<DepositBond>, amount: u64) -> Result<()> {
+        require!(amount > 0, ErrorCode::InvalidAmount);
+
+        let owner_bond = &mut ctx.accounts.owner_bond;
+        if owner_bond.owner == Pubkey::default() {
+            owner_bond.owner = ctx.accounts.owner.key();
+            owner_bond.mint = ctx.accounts.usdc_mint.key();
+            owner_bond.bump = ctx.bumps.owner_bond;
+        }
+        require_keys_eq!(owner_bond.mint, ctx.accounts.usdc_mint.key(), ErrorCode::InvalidBondMint);
+        // Topping up the bond withdraws any pending withdrawal request
+        owner_bond.withdrawal_requested_at = None;
+
+        transfer_to_escrow(
+            &ctx.accounts.owner_token_account,
+            &ctx.accounts.bond_vault,
+            &ctx.accounts.owner,
+            &ctx.accounts.token_program,
+            amount,
+        )?;
+
+        emit!(OwnerBondDeposited {
+            owner: owner_bond.owner,
+            amount,
+            balance: ctx.accounts.bond_vault.amount
+                .checked_add(amount)
+                .ok_or(ErrorCode::MathOverflow)?,
+        });
+
+        Ok(())
+    }
+
+    pub fn request_bond_withdrawal(ctx: Context<RequestBondWithdrawal>) -> Result<()> {
+        let current_time = Clock::get()?.unix_timestamp;
+        require!(
+            current_time >= ctx.accounts.owner_profile.bond_locked_until,
+            ErrorCode::BondLocked
+        );
+
+        let owner_bond = &mut ctx.accounts.owner_bond;
+        owner_bond.withdrawal_requested_at = Some(current_time);
+
+        let available_at = current_time
+            .checked_add(ctx.accounts.global_state.bond_withdrawal_cooldown)
+            .ok_or(ErrorCode::MathOverflow)?;
+        emit!(OwnerBondWithdrawalRequested {
+            owner: owner_bond.owner,
+            available_at,
+            expires_at: available_at
+                .checked_add(BOND_WITHDRAWAL_WINDOW)
+                .ok_or(ErrorCode::MathOverflow)?,
+        });
+
+        Ok(())
+    }
+
+    pub fn withdraw_bond(ctx: Context<WithdrawBond>, amount: u64) -> Result<()> {
+        let current_time = Clock::get()?.unix_timestamp;
+        let owner_bond = &ctx.accounts.owner_bond;
+
+        require_keys_eq!(owner_bond.mint, ctx.accounts.usdc_mint.key(), ErrorCode::InvalidBondMint);
+        // Bookings made after the request was filed keep the bond locked as well
+        require!(
+            current_time >= ctx.accounts.owner_profile.bond_locked_until,
+            ErrorCode::BondLocked
+        );
+
+        let requested_at = owner_bond.withdrawal_requested_at
+            .ok_or(ErrorCode::BondWithdrawalNotRequested)?;
+        let available_at = requested_at
+            .checked_add(ctx.accounts.global_state.bond_withdrawal_cooldown)
+            .ok_or(ErrorCode::MathOverflow)?;
+        require!(current_time >= available_at, ErrorCode::BondCooldownActive);
+        // A request can only be used shortly after its cooldown, so the owner cannot
+        // hold one open and pull the bond just ahead of a penalty
+        require!(
+            current_time < available_at
+                .checked_add(BOND_WITHDRAWAL_WINDOW)
+                .ok_or(ErrorCode::MathOverflow)?,
+            ErrorCode::BondWithdrawalExpired
+        );
+        require!(
+            amount > 0 && amount <= ctx.accounts.bond_vault.amount,
+            ErrorCode::InvalidAmount
+        );
+
+        transfer_from_bond(
+            owner_bond,
+            &ctx.accounts.bond_vault,
+            ctx.accounts.owner_token_account.to_account_info(),
+            &ctx.accounts.token_program,
+            amount,
+        )?;
+
+        let owner_bond = &mut ctx.accounts.owner_bond;
+        owner_bond.withdrawal_requested_at = None;
+
+        emit!(OwnerBondWithdrawn {
+            owner: owner_bond.owner,
+            amount,
+            balance: ctx.accounts.bond_vault.amount
+                .checked_sub(amount)
+                .ok_or(ErrorCode::MathOverflow)?,
+        });
+
+        Ok(())
+    }
+
+    // Either party can record the handover; the other party can contest it while the
+    // contest window is open
+    pub fn record_handover(ctx: Context<RecordHandover>) -> Result<()> {
+        let rental_transaction = &mut ctx.accounts.rental_transaction;
+        let recorded_by = ctx.accounts.signer.key();
+        let current_time = Clock::get()?.unix_timestamp;
+
+        require!(rental_transaction.is_party(&recorded_by), ErrorCode::UnauthorizedHandover);
+        require!(
+            rental_transaction.status == TransactionStatus::Paid ||
+            rental_transaction.status == TransactionStatus::PartiallyPaid,
+            ErrorCode::InvalidTransactionStatus
+        );
+        require!(
+            rental_transaction.handed_over_at.is_none(),
+            ErrorCode::HandoverAlreadyRecorded
+        );
+
+        rental_transaction.handed_over_at = Some(current_time);
+        rental_transaction.handover_recorded_by = Some(recorded_by);
+
+        emit!(HandoverRecorded {
+            booking_id: rental_transaction.booking_id.clone(),
+            recorded_by,
+            handed_over_at: current_time,
+            contest_deadline: current_time
+                .checked_add(ctx.accounts.global_state.no_show_contest_window)
+                .ok_or(ErrorCode::MathOverflow)?,
+        });
+
+        Ok(())
+    }
+
+    // A handover recorded by one party can be turned into a regular dispute by the other
+    pub fn contest_handover(ctx: Context<ContestHandover>) -> Result<()> {
+        let rental_transaction = &mut ctx.accounts.rental_transaction;
+        let signer = ctx.accounts.signer.key();
+        let current_time = Clock::get()?.unix_timestamp;
+
+        require!(
+            rental_transaction.status == TransactionStatus::Paid ||
+            rental_transaction.status == TransactionStatus::PartiallyPaid,
+            ErrorCode::InvalidTransactionStatus
+        );
+        rental_transaction.require_contestable_by(
+            &signer,
+            rental_transaction.handover_recorded_by,
+            rental_transaction.handed_over_at,
+            ctx.accounts.global_state.no_show_contest_window,
+            current_time,
+        )?;
+
+        rental_transaction.status = TransactionStatus::Disputed;
+        rental_transaction.dispute_opened_by = Some(signer);
+
+        let opener_profile = &mut ctx.accounts.opener_profile;
+        opener_profile.disputes_opened = opener_profile.disputes_opened
+            .checked_add(1)
+            .ok_or(ErrorCode::MathOverflow)?;
+
+        emit!(DisputeOpened {
+            booking_id: rental_transaction.booking_id.clone(),
+            opened_by: signer,
+            opened_at: current_time,
+        });
+
+        Ok(())
+    }
+
+    // Either party can claim that the other never showed up; who claims decides how
+    // the escrow is settled once the claim is finalized
+    pub fn claim_no_show(ctx: Context<ClaimNoShow>) -> Result<()> {
+        let rental_transaction = &mut ctx.accounts.rental_transaction;
+        let claimant = ctx.accounts.claimant.key();
+        let current_time = Clock::get()?.unix_timestamp;
+
+        require!(
+            rental_transaction.is_party(&claimant),
+            ErrorCode::UnauthorizedNoShowClaim
+        );
+        rental_transaction.require_no_show_claimable(
+            current_time,
+            ctx.accounts.global_state.no_show_window,
+        )?;
+
+        rental_transaction.status = TransactionStatus::NoShowClaimed;
+        rental_transaction.no_show_claimed_by = Some(claimant);
+        rental_transaction.no_show_claimed_at = Some(current_time);
+
+        emit!(NoShowClaimed {
+            booking_id: rental_transaction.booking_id.clone(),
+            claimant,
+            claimed_at: current_time,
+            contest_deadline: current_time
+                .checked_add(ctx.accounts.global_state.no_show_contest_window)
+                .ok_or(ErrorCode::MathOverflow)?,
+        });
+
+        Ok(())
+    }
+
+    // The other party can turn a no-show claim into a regular dispute for the admin
+    pub fn contest_no_show(ctx: Context<ContestNoShow>) -> Result<()> {
+        let rental_transaction = &mut ctx.accounts.rental_transaction;
+        let signer = ctx.accounts.signer.key();
+        let current_time = Clock::get()?.unix_timestamp;
+
+        require!(
+            rental_transaction.status == TransactionStatus::NoShowClaimed,
+            ErrorCode::InvalidTransactionStatus
+        );
+        rental_transaction.require_contestable_by(
+            &signer,
+            rental_transaction.no_show_claimed_by,
+            rental_transaction.no_show_claimed_at,
+            ctx.accounts.global_state.no_show_contest_window,
+            current_time,
+        )?;
+
+        rental_transaction.status = TransactionStatus::Disputed;
+        rental_transaction.dispute_opened_by = Some(signer);
+
+        let opener_profile = &mut ctx.accounts.opener_profile;
+        opener_profile.disputes_opened = opener_profile.disputes_opened
+            .checked_add(1)
+            .ok_or(ErrorCode::MathOverflow)?;
+
+        emit!(DisputeOpened {
+            booking_id: rental_transaction.booking_id.clone(),
+            opened_by: signer,
+            opened_at: current_time,
+        });
+
+        Ok(())
+    }
+
+    pub fn finalize_no_show<'info>(
+        ctx: Context<'_, '_, 'info, 'info, FinalizeNoShow<'info>>,
+    ) -> Result<()> {
+        let current_time = Clock::get()?.unix_timestamp;
+        let rental = &ctx.accounts.rental_transaction;
+        let finalized_by = ctx.accounts.signer.key();
+
+        require!(
+            rental.status == TransactionStatus::NoShowClaimed,
+            ErrorCode::InvalidTransactionStatus
+        );
+        // Either party can finalize once the contest window has closed, so the
+        // claimant cannot hold the escrow by never finalizing
+        require!(rental.is_party(&finalized_by), ErrorCode::UnauthorizedNoShowClaim);
+        let claimant = rental.no_show_claimed_by
+            .ok_or(ErrorCode::InvalidTransactionStatus)?;
+        let claimed_at = rental.no_show_claimed_at
+            .ok_or(ErrorCode::InvalidTransactionStatus)?;
+        require!(
+            current_time > claimed_at
+                .checked_add(ctx.accounts.global_state.no_show_contest_window)
+                .ok_or(ErrorCode::MathOverflow)?,
+            ErrorCode::NoShowContestWindowOpen
+        );
+
+        let Settlement {
+            renter_refund,
+            owner_share,
+            service_fee_refund,
+            service_fee_retained,
+        } = rental.no_show_settlement(&claimant)?;
+        let (renter_refund_share, subsidy_refund) = rental.split_refund(renter_refund)?;
+        let refund_amount = renter_refund_share
+            .checked_add(service_fee_refund)
+            .ok_or(ErrorCode::MathOverflow)?;
+
+        transfer_from_escrow(
+            &ctx.accounts.rental_transaction,
+            &ctx.accounts.escrow_token_account,
+            ctx.accounts.renter_token_account.to_account_info(),
+            &ctx.accounts.token_program,
+            refund_amount,
+        )?;
+
+        transfer_from_escrow(
+            &ctx.accounts.rental_transaction,
+            &ctx.accounts.escrow_token_account,
+            ctx.accounts.treasury_token_account.to_account_info(),
+            &ctx.accounts.token_program,
+            service_fee_retained
+                .checked_add(subsidy_refund)
+                .ok_or(ErrorCode::MathOverflow)?,
+        )?;
+
+        let (owner_amount, platform_fee) = if owner_share > 0 {
+            let category_fee = load_category_fee(
+                &ctx.accounts.rental_transaction,
+                ctx.accounts.category_fee.as_ref(),
+            )?;
+            let fee_tiers = load_config::<FeeTierConfig>(&ctx.accounts.fee_tiers)?;
+            let platform_fee_rate = resolve_platform_fee_rate(
+                &ctx.accounts.global_state,
+                category_fee.as_ref(),
+                fee_tiers.as_ref(),
+                ctx.accounts.owner_profile.owner_volume,
+            );
+            let fee_clamp = load_config::<FeeClampConfig>(&ctx.accounts.fee_clamp)?;
+            release_owner_earnings(
+                &ctx.accounts.rental_transaction,
+                &ctx.accounts.escrow_token_account,
+                &ctx.accounts.owner_token_account,
+                &ctx.accounts.treasury_token_account,
+                ctx.accounts.referrer_account.as_mut(),
+                ctx.accounts.referrer_token_account.as_ref(),
+                ctx.remaining_accounts,
+                &ctx.accounts.token_program,
+                owner_share,
+                platform_fee_rate,
+                ctx.accounts.global_state.referral_fee_share,
+                fee_clamp.as_ref(),
+            )?
+        } else {
+            (0, 0)
+        };
+
+        let penalty_paid = if claimant == ctx.accounts.rental_transaction.renter {
+            // The renter supplies the owner's bond when one exists; an owner finalizing
+            // against themselves must supply it so the penalty cannot be skipped
+            require!(
+                finalized_by == claimant ||
+                (ctx.accounts.owner_bond.is_some() && ctx.accounts.bond_vault.is_some()),
+                ErrorCode::OwnerBondRequired
+            );
+            let penalty = ctx.accounts.global_state.owner_penalty(&ctx.accounts.rental_transaction)?;
+            let penalty_paid = match (ctx.accounts.owner_bond.as_mut(), ctx.accounts.bond_vault.as_ref()) {
+                (Some(owner_bond), Some(bond_vault)) => {
+                    require_keys_eq!(owner_bond.mint, ctx.accounts.usdc_mint.key(), ErrorCode::InvalidBondMint);
+                    pay_bond_penalty(
+                        owner_bond,
+                        bond_vault,
+                        ctx.accounts.renter_token_account.to_account_info(),
+                        &ctx.accounts.token_program,
+                        penalty,
+                    )?
+                }
+                _ => 0,
+            };
+            ctx.accounts.owner_profile.record_cancellation()?;
+            penalty_paid
+        } else {
+            ctx.accounts.renter_profile.record_cancellation()?;
+            0
+        };
+
+        let rental_transaction = &mut ctx.accounts.rental_transaction;
+        rental_transaction.released_amount = rental_transaction.released_amount
+            .checked_add(owner_share)
+            .ok_or(ErrorCode::MathOverflow)?;
+        rental_transaction.released_fee = rental_transaction.released_fee
+            .checked_add(platform_fee)
+            .ok_or(ErrorCode::MathOverflow)?;
+        rental_transaction.status = TransactionStatus::Cancelled;
+        rental_transaction.completed_at = Some(current_time);
+
+        emit!(NoShowFinalized {
+            booking_id: rental_transaction.booking_id.clone(),
+            claimant,
+            finalized_by,
+            refund_amount,
+            owner_amount,
+            penalty: penalty_paid,
+            finalized_at: current_time,
+        });
+
+        Ok(())
+    }
+
+    pub fn open_dispute(ctx: Context<OpenDispute>) -> Result<()> {
+        let rental_transaction = &mut ctx.accounts.rental_transaction;
+        let signer = ctx.accounts.signer.key();
+        let current_time = Clock::get()?.unix_timestamp;
+
+        require!(rental_transaction.is_disputable(), ErrorCode::InvalidTransactionStatus);
+        require!(rental_transaction.is_party(&signer), ErrorCode::UnauthorizedDispute);
+
+        rental_transaction.status = TransactionStatus::Disputed;
+        rental_transaction.dispute_opened_by = Some(signer);
+
+        let opener_profile = &mut ctx.accounts.opener_profile;
+        opener_profile.disputes_opened = opener_profile.disputes_opened
+            .checked_add(1)
+            .ok_or(ErrorCode::MathOverflow)?;
+
+        emit!(DisputeOpened {
+            booking_id: rental_transaction.booking_id.clone(),
+            opened_by: signer,
+            opened_at: current_time,
+        });
+
+        Ok(())
+    }
+
+    pub fn submit_review(
+        ctx: Context<SubmitReview>,
+        rating: u8,
+        content_hash: [u8; 32],
+    ) -> Result<()> {
+        let rental_transaction = &ctx.accounts.rental_transaction;
+        let reviewer = ctx.accounts.reviewer.key();
+        let current_time = Clock::get()?.unix_timestamp;
+
+        require!(rental_transaction.is_reviewable(), ErrorCode::InvalidTransactionStatus);
+        require!((1..=5).contains(&rating), ErrorCode::InvalidRating);
+
+        let reviewee = rental_transaction
+            .counterparty(&reviewer)
+            .ok_or(ErrorCode::UnauthorizedReview)?;
+        require!(
+            ctx.accounts.reviewee_profile.wallet == reviewee,
+            ErrorCode::UnauthorizedReview
+        );
+
+        let review = &mut ctx.accounts.review;
+        review.rental_transaction = rental_transaction.key();
+        review.reviewer = reviewer;
+        review.reviewee = reviewee;
+        review.rating = rating;
+        review.content_hash = content_hash;
+        review.created_at = current_time;
+        review.bump = ctx.bumps.review;
+
+        let reviewee_profile = &mut ctx.accounts.reviewee_profile;
+        reviewee_profile.reviews_received = reviewee_profile.reviews_received
+            .checked_add(1)
+            .ok_or(ErrorCode::MathOverflow)?;
+        reviewee_profile.rating_total = reviewee_profile.rating_total
+            .checked_add(rating as u64)
+            .ok_or(ErrorCode::MathOverflow)?;
+
+        emit!(ReviewSubmitted {
+            booking_id: rental_transaction.booking_id.clone(),
+            reviewer,
+            reviewee,
+            rating,
+            content_hash,
+        });
+
+        Ok(())
+    }
+
+    pub fn set_payout_splits(
+        ctx: Context<SetPayoutSplits>,
+        payout_splits: Vec<PayoutSplit>,
+    ) -> Result<()> {
+        let rental_transaction = &mut ctx.accounts.rental_transaction;
+
+        require!(
+            ctx.accounts.owner.key() == rental_transaction.owner_wallet,
+            ErrorCode::UnauthorizedPayoutSplits
+        );
+        require!(
+            rental_transaction.status == TransactionStatus::Created ||
+            rental_transaction.status == TransactionStatus::Paid,
+            ErrorCode::InvalidTransactionStatus
+        );
+        require!(
+            payout_splits.len() <= MAX_PAYOUT_SPLITS,
+            ErrorCode::TooManyPayoutSplits
+        );
+
+        let mut total_bps: u16 = 0;
+        for split in payout_splits.iter() {
+            require!(
+                split.bps > 0 && split.recipient != Pubkey::default(),
+                ErrorCode::InvalidPayoutSplit
+            );
+            total_bps = total_bps
+                .checked_add(split.bps)
+                .ok_or(ErrorCode::MathOverflow)?;
+        }
+        require!(total_bps <= 10000, ErrorCode::InvalidPercentages);
+
+        rental_transaction.payout_splits = payout_splits.clone();
+
+        emit!(PayoutSplitsUpdated {
+            booking_id: rental_transaction.booking_id.clone(),
+            payout_splits,
+        });
+
+        Ok(())
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    pub fn redeem_offer(
+        ctx: Context<RedeemOffer>,
+        product_id: u64,
+        owner_wallet: Pubkey,
+        nonce: u64,
+        total_amount: u64,
+        rental_start: i64,
+        rental_end: i64,
+        expires_at: i64,
+        booking_id: String,
+    ) -> Result<()> {
+        let renter = ctx.accounts.renter.key();
+        let current_time = Clock::get()?.unix_timestamp;
+
+        RentalTransaction::validate_booking(
+            renter,
+            owner_wallet,
+            total_amount,
+            rental_start,
+            rental_end,
+            &booking_id,
+        )?;
+        require!(current_time <= expires_at, ErrorCode::OfferExpired);
+
+        let offer = OwnerOffer {
+            renter,
+            owner_wallet,
+            product_id,
+            total_amount,
+            rental_start,
+            rental_end,
+            expires_at,
+            nonce,
+        };
+        verify_ed25519_instruction(
+            &ctx.accounts.instructions_sysvar,
+            &owner_wallet,
+            &offer.message(),
+        )?;
+
+        let product = load_config::<Product>(&ctx.accounts.product)?;
+        let (category_id, policies) = match &product {
+            Some(product) => {
+                require!(product.owner == owner_wallet, ErrorCode::ProductOwnerMismatch);
+                (product.category_id, product.policies.clone())
+            }
+            None => (None, ProductPolicies::default()),
+        };
+
+        let used_offer_nonce = &mut ctx.accounts.used_offer_nonce;
+        used_offer_nonce.owner = owner_wallet;
+        used_offer_nonce.nonce = nonce;
+        used_offer_nonce.rental_transaction = ctx.accounts.rental_transaction.key();
+        used_offer_nonce.used_at = current_time;
+        used_offer_nonce.bump = ctx.bumps.used_offer_nonce;
+
+        ctx.accounts.renter_profile.init_if_empty(renter, ctx.bumps.renter_profile);
+        ctx.accounts.owner_profile.init_if_empty(owner_wallet, ctx.bumps.owner_profile);
+        ctx.accounts.owner_profile.lock_bond_until(rental_start);
+
+        let fee_clamp = load_config::<FeeClampConfig>(&ctx.accounts.fee_clamp)?;
+        let service_fee = calculate_service_fee(
+            total_amount,
+            &ctx.accounts.global_state,
+            fee_clamp.as_ref(),
+        )?;
+        let amount_due = total_amount
+            .checked_add(service_fee)
+            .ok_or(ErrorCode::MathOverflow)?;
+
+        transfer_to_escrow(
+            &ctx.accounts.renter_token_account,
+            &ctx.accounts.escrow_token_account,
+            &ctx.accounts.renter,
+            &ctx.accounts.token_program,
+            amount_due,
+        )?;
+
+        let rental_transaction = &mut ctx.accounts.rental_transaction;
+        rental_transaction.initialize(
+            product_id,
+            renter,
+            owner_wallet,
+            total_amount,
+            rental_start,
+            rental_end,
+            booking_id,
+            ctx.bumps.rental_transaction,
+        )?;
+        rental_transaction.category_id = category_id;
+        rental_transaction.policies = policies;
+        rental_transaction.status = TransactionStatus::Paid;
+        rental_transaction.paid_at = Some(current_time);
+        rental_transaction.escrow_bump = ctx.bumps.escrow_token_account;
+        rental_transaction.service_fee = service_fee;
+        rental_transaction.amount_paid = total_amount;
+
+        emit!(RentalTransactionCreated {
+            product_id,
+            renter,
+            owner_wallet,
+            total_amount,
+            booking_id: rental_transaction.booking_id.clone(),
+        });
+
+        emit!(RentalPaymentCompleted {
+            booking_id: rental_transaction.booking_id.clone(),
+            amount: total_amount,
+            service_fee,
+            renter,
+        });
+
+        emit!(OfferRedeemed {
+            booking_id: rental_transaction.booking_id.clone(),
+            owner: owner_wallet,
+            renter,
+            nonce,
+            total_amount,
+        });
+
+        Ok(())
+    }
+}
+
+#[derive(Accounts)]
+pub struct Initialize<'info> {
+    #[account(
+        init,
+        payer = admin,
+        space = GlobalState::SPACE,
+        seeds = [b"global_state"],
+        bump
+    )]
+    pub global_state: Account<'info, GlobalState>,
+    
+    #[account(mut)]
+    pub admin: Signer<'info>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct MigrateGlobalState<'info> {
+    /// CHECK: the pre-upgrade global state, which only deserializes once it is resized
+    #[account(
+        mut,
+        seeds = [b"global_state"],
+        bump,
+        owner = crate::ID
+    )]
+    pub global_state: UncheckedAccount<'info>,
+
+    #[account(mut)]
+    pub admin: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct UpdateGlobalState<'info> {
+    #[account(
+        mut,
+        seeds = [b"global_state"],
+        bump = global_state.bump
+    )]
+    pub global_state: Account<'info, GlobalState>,
+
+    pub admin: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SetFeeClamp<'info> {
+    #[account(
+        init_if_needed,
+        payer = admin,
+        space = 8 + 32 + 8 + 8 + 1,
+        seeds = [b"fee_clamp", usdc_mint.key().as_ref()],
+        bump
+    )]
+    pub fee_clamp: Account<'info, FeeClampConfig>,
+
+    #[account(
+        seeds = [b"global_state"],
+        bump = global_state.bump
+    )]
+    pub global_state: Account<'info, GlobalState>,
+
+    pub usdc_mint: Account<'info, Mint>,
+
+    #[account(mut)]
+    pub admin: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct SetFeeTiers<'info> {
+    #[account(
+        init_if_needed,
+        payer = admin,
+        space = 8 + (4 + MAX_FEE_TIERS * (8 + 2)) + 1,
+        seeds = [b"fee_tiers"],
+        bump
+    )]
+    pub fee_tiers: Account<'info, FeeTierConfig>,
+
+    #[account(
+        seeds = [b"global_state"],
+        bump = global_state.bump
+    )]
+    pub global_state: Account<'info, GlobalState>,
+
+    #[account(mut)]
+    pub admin: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(category_id: u64)]
+pub struct SetCategoryFee<'info> {
+    #[account(
+        init_if_needed,
+        payer = admin,
+        space = 8 + 8 + 2 + 1,
+        seeds = [b"category_fee", &category_id.to_le_bytes()[..8]],
+        bump
+    )]
+    pub category_fee: Account<'info, CategoryFeeConfig>,
+
+    #[account(
+        seeds = [b"global_state"],
+        bump = global_state.bump
+    )]
+    pub global_state: Account<'info, GlobalState>,
+
+    #[account(mut)]
+    pub admin: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct RemoveCategoryFee<'info> {
+    #[account(
+        mut,
+        close = admin,
+        seeds = [b"category_fee", &category_fee.category_id.to_le_bytes()[..8]],
+        bump = category_fee.bump
+    )]
+    pub category_fee: Account<'info, CategoryFeeConfig>,
+
+    #[account(
+        seeds = [b"global_state"],
+        bump = global_state.bump
+    )]
+    pub global_state: Account<'info, GlobalState>,
+
+    #[account(mut)]
+    pub admin: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(code: String)]
+pub struct CreateCoupon<'info> {
+    #[account(
+        init,
+        payer = issuer,
+        space = 8 + (4 + MAX_COUPON_CODE_LEN) + 32 + 1 + (1 + 8) + 4 + 4 + 4 + 8 + (1 + 8) + (1 + 8) + 1,
+        seeds = [b"coupon", issuer.key().as_ref(), code.as_bytes()],
+        bump
+    )]
+    pub coupon: Account<'info, Coupon>,
+
+    #[account(
+        seeds = [b"global_state"],
+        bump = global_state.bump
+    )]
+    pub global_state: Account<'info, GlobalState>,
+
+    #[account(mut)]
+    pub issuer: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct RegisterReferrer<'info> {
+    #[account(
+        init,
+        payer = wallet,
+        space = 8 + 32 + 8 + 8 + 8 + 1,
+        seeds = [b"referrer", wallet.key().as_ref()],
+        bump
+    )]
+    pub referrer_account: Account<'info, Referrer>,
+
+    #[account(mut)]
+    pub wallet: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct InitializeTreasury<'info> {
+    #[account(
+        init,
+        payer = admin,
+        seeds = [b"treasury", usdc_mint.key().as_ref()],
+        bump,
+        token::mint = usdc_mint,
+        token::authority = global_state
+    )]
+    pub treasury_token_account: Account<'info, TokenAccount>,
+
+    #[account(
+        seeds = [b"global_state"],
+        bump = global_state.bump
+    )]
+    pub global_state: Account<'info, GlobalState>,
+
+    pub usdc_mint: Account<'info, Mint>,
+
+    #[account(mut)]
+    pub admin: Signer<'info>,
+
+    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct AddTreasuryDestination<'info> {
+    #[account(
+        init,
+        payer = admin,
+        space = 8 + 32 + 32 + 1,
+        seeds = [b"treasury_destination", destination_token_account.key().as_ref()],
+        bump
+    )]
+    pub treasury_destination: Account<'info, TreasuryDestination>,
+
+    pub destination_token_account: Account<'info, TokenAccount>,
+
+    #[account(
+        seeds = [b"global_state"],
+        bump = global_state.bump
+    )]
+    pub global_state: Account<'info, GlobalState>,
+
+    #[account(mut)]
+    pub admin: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct RemoveTreasuryDestination<'info> {
+    #[account(
+        mut,
+        close = admin,
+        seeds = [b"treasury_destination", treasury_destination.token_account.as_ref()],
+        bump = treasury_destination.bump
+    )]
+    pub treasury_destination: Account<'info, TreasuryDestination>,
+
+    #[account(
+        seeds = [b"global_state"],
+        bump = global_state.bump
+    )]
+    pub global_state: Account<'info, GlobalState>,
+
+    #[account(mut)]
+    pub admin: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct WithdrawTreasury<'info> {
+    #[account(
+        mut,
+        seeds = [b"treasury", usdc_mint.key().as_ref()],
+        bump,
+        token::mint = usdc_mint,
+        token::authority = global_state
+    )]
+    pub treasury_token_account: Account<'info, TokenAccount>,
+
+    #[account(
+        seeds = [b"treasury_destination", destination_token_account.key().as_ref()],
+        bump = treasury_destination.bump
+    )]
+    pub treasury_destination: Account<'info, TreasuryDestination>,
+
+    #[account(
+        mut,
+        token::mint = usdc_mint
+    )]
+    pub destination_token_account: Account<'info, TokenAccount>,
+
+    #[account(
+        seeds = [b"global_state"],
+        bump = global_state.bump
+    )]
+    pub global_state: Account<'info, GlobalState>,
+
+    pub usdc_mint: Account<'info, Mint>,
+    pub admin: Signer<'info>,
+    pub token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+#[instruction(product_id: u64)]
+pub struct CreateProduct<'info> {
+    #[account(
+        init,
+        payer = owner,
+        space = 8 + 8 + 32 + (8 + 8 + 8 + 2 + 2 + 2) + (1 + 8) + ProductPolicies::SPACE + 1,
+        seeds = [b"product", owner.key().as_ref(), &product_id.to_le_bytes()[..8]],
+
+        bump
+    )]
+    pub product: Account<'info, Product>,
+
+    #[account(mut)]
+    pub owner: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct UpdateProduct<'info> {
+    #[account(
+        mut,
+        seeds = [b"product", product.owner.as_ref(), &product.product_id.to_le_bytes()[..8]],
+        bump = product.bump
+    )]
+    pub product: Account<'info, Product>,
+
+    pub owner: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(product_id: u64, owner_wallet: Pubkey)]
+pub struct CreateRentalTransaction<'info> {
+    #[account(
+        init,
+        payer = renter,
+        space = RentalTransaction::SPACE,
+        seeds = [b"rental_transaction", &product_id.to_le_bytes()[..8], &renter.key().to_bytes()],
+        bump
+    )]
+    pub rental_transaction: Account<'info, RentalTransaction>,
+
+    #[account(
+        init_if_needed,
+        payer = renter,
+        space = UserProfile::SPACE,
+        seeds = [b"user_profile", renter.key().as_ref()],
+        bump
+    )]
+    pub renter_profile: Account<'info, UserProfile>,
+
+    #[account(
+        init_if_needed,
+        payer = renter,
+        space = UserProfile::SPACE,
+        seeds = [b"user_profile", owner_wallet.as_ref()],
+        bump
+    )]
+    pub owner_profile: Account<'info, UserProfile>,
+    
+    /// CHECK: the owner's product PDA; bookings are priced from it once it exists
+    #[account(
+        seeds = [b"product", owner_wallet.as_ref(), &product_id.to_le_bytes()[..8]],
+        bump
+    )]
+    pub product: UncheckedAccount<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"referrer", referrer_account.wallet.as_ref()],
+        bump = referrer_account.bump
+    )]
+    pub referrer_account: Option<Account<'info, Referrer>>,
+
+    #[account(
+        seeds = [b"global_state"],
+        bump = global_state.bump
+    )]
+    pub global_state: Account<'info, GlobalState>,
+
+    /// CHECK: address is constrained to the instructions sysvar
+    #[account(address = anchor_lang::solana_program::sysvar::instructions::ID)]
+    pub instructions_sysvar: UncheckedAccount<'info>,
+
+    #[account(mut)]
+    pub renter: Signer<'info>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(wallet: Pubkey)]
+pub struct InitUserProfile<'info> {
+    #[account(
+        init,
+        payer = payer,
+        space = UserProfile::SPACE,
+        seeds = [b"user_profile", wallet.as_ref()],
+        bump
+    )]
+    pub user_profile: Account<'info, UserProfile>,
+
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct MigrateRentalTransaction<'info> {
+    /// CHECK: a pre-upgrade rental, which only deserializes once it is resized
+    #[account(mut, owner = crate::ID)]
+    pub rental_transaction: UncheckedAccount<'info>,
+
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct MigrateUserProfile<'info> {
+    /// CHECK: a pre-upgrade profile, which only deserializes once it is resized
+    #[account(mut, owner = crate::ID)]
+    pub user_profile: UncheckedAccount<'info>,
+
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(
+    product_id: u64,
+    owner_wallet: Pubkey,
+    total_amount: u64,
+    rental_start: i64,
+    rental_end: i64,
+    booking_id: String,
+    referrer: Option<Pubkey>,
+    category_id: Option<u64>,
+    quote_expires_at: Option<i64>,
+    coupon_key: Option<Pubkey>,
+)]
+pub struct BookAndPay<'info> {
+    #[account(
+        init,
+        payer = renter,
+        space = RentalTransaction::SPACE,
+        seeds = [b"rental_transaction", &product_id.to_le_bytes()[..8], &renter.key().to_bytes()],
+        bump
+    )]
+    pub rental_transaction: Box<Account<'info, RentalTransaction>>,
+
+    #[account(
+        init,
+        payer = renter,
+        seeds = [b"escrow", rental_transaction.key().as_ref()],
+        bump,
+        token::mint = usdc_mint,
+        token::authority = rental_transaction
+    )]
+    pub escrow_token_account: Box<Account<'info, TokenAccount>>,
+
+    #[account(
+        init_if_needed,
+        payer = renter,
+        space = UserProfile::SPACE,
+        seeds = [b"user_profile", renter.key().as_ref()],
+        bump
+    )]
+    pub renter_profile: Box<Account<'info, UserProfile>>,
+
+    #[account(
+        init_if_needed,
+        payer = renter,
+        space = UserProfile::SPACE,
+        seeds = [b"user_profile", owner_wallet.as_ref()],
+        bump
+    )]
+    pub owner_profile: Box<Account<'info, UserProfile>>,
+
+    /// CHECK: the owner's product PDA; bookings are priced from it once it exists
+    #[account(
+        seeds = [b"product", owner_wallet.as_ref(), &product_id.to_le_bytes()[..8]],
+        bump
+    )]
+    pub product: UncheckedAccount<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"referrer", referrer_account.wallet.as_ref()],
+        bump = referrer_account.bump
+    )]
+    pub referrer_account: Option<Account<'info, Referrer>>,
+
+    #[account(
+        mut,
+        associated_token::mint = usdc_mint,
+        associated_token::authority = renter
+    )]
+    pub renter_token_account: Box<Account<'info, TokenAccount>>,
+
+    #[account(
+        mut,
+        seeds = [b"coupon", coupon.issuer.as_ref(), coupon.code.as_bytes()],
+        bump = coupon.bump
+    )]
+    pub coupon: Option<Box<Account<'info, Coupon>>>,
+
+    #[account(
+        init_if_needed,
+        payer = renter,
+        space = 8 + 32 + 32 + 4 + 1,
+        seeds = [b"coupon_redemption", coupon_key.unwrap_or_default().as_ref(), renter.key().as_ref()],
+        bump
+    )]
+    pub coupon_redemption: Option<Box<Account<'info, CouponRedemption>>>,
+
+    #[account(
+        mut,
+        seeds = [b"treasury", usdc_mint.key().as_ref()],
+        bump,
+        token::mint = usdc_mint,
+        token::authority = global_state
+    )]
+    pub treasury_token_account: Option<Box<Account<'info, TokenAccount>>>,
+
+    /// CHECK: canonical fee clamp PDA for the mint, read as unset until the admin configures it
+    #[account(
+        seeds = [b"fee_clamp", usdc_mint.key().as_ref()],
+        bump
+    )]
+    pub fee_clamp: UncheckedAccount<'info>,
+
+    #[account(
+        seeds = [b"global_state"],
+        bump = global_state.bump
+    )]
+    pub global_state: Box<Account<'info, GlobalState>>,
+
+    /// CHECK: address is constrained to the instructions sysvar
+    #[account(address = anchor_lang::solana_program::sysvar::instructions::ID)]
+    pub instructions_sysvar: UncheckedAccount<'info>,
+
+    pub usdc_mint: Box<Account<'info, Mint>>,
+
+    #[account(mut)]
+    pub renter: Signer<'info>,
+
+    pub token_program: Program<'info, Token>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(amount: u64, coupon_key: Option<Pubkey>)]
+pub struct PayRental<'info> {
+    #[account(
+        mut,
+        seeds = [b"rental_transaction", &rental_transaction.product_id.to_le_bytes()[..8], &rental_transaction.renter.to_bytes()],
+        bump = rental_transaction.bump
+    )]
+    pub rental_transaction: Account<'info, RentalTransaction>,
+    
+    #[account(
+        init_if_needed,
+        payer = renter,
+        seeds = [b"escrow", rental_transaction.key().as_ref()],
+        bump,
+        token::mint = usdc_mint,
+        token::authority = rental_transaction
+    )]
+    pub escrow_token_account: Account<'info, TokenAccount>,
+    
+    #[account(
+        mut,
+        associated_token::mint = usdc_mint,
+        associated_token::authority = renter
+    )]
+    pub renter_token_account: Account<'info, TokenAccount>,
+    
+    #[account(
+        mut,
+        seeds = [b"coupon", coupon.issuer.as_ref(), coupon.code.as_bytes()],
+        bump = coupon.bump
+    )]
+    pub coupon: Option<Account<'info, Coupon>>,
+    
+    #[account(
+        init_if_needed,
+        payer = renter,
+        space = 8 + 32 + 32 + 4 + 1,
+        seeds = [b"coupon_redemption", coupon_key.unwrap_or_default().as_ref(), renter.key().as_ref()],
+        bump
+    )]
+    pub coupon_redemption: Option<Account<'info, CouponRedemption>>,
+    
+    #[account(
+        mut,
+        seeds = [b"treasury", usdc_mint.key().as_ref()],
+        bump,
+        token::mint = usdc_mint,
+        token::authority = global_state
+    )]
+    pub treasury_token_account: Option<Account<'info, TokenAccount>>,
+    
+    /// CHECK: canonical fee clamp PDA for the mint, read as unset until the admin configures it
+    #[account(
+        seeds = [b"fee_clamp", usdc_mint.key().as_ref()],
+        bump
+    )]
+    pub fee_clamp: UncheckedAccount<'info>,
+    
+    #[account(
+        seeds = [b"global_state"],
+        bump = global_state.bump
+    )]
+    pub global_state: Account<'info, GlobalState>,
+    
+    pub usdc_mint: Account<'info, Mint>,
+    
+    #[account(mut)]
+    pub renter: Signer<'info>,
+    
+    pub token_program: Program<'info, Token>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct PayPartial<'info> {
+    #[account(
+        mut,
+        seeds = [b"rental_transaction", &rental_transaction.product_id.to_le_bytes()[..8], &rental_transaction.renter.to_bytes()],
+        bump = rental_transaction.bump
+    )]
+    pub rental_transaction: Account<'info, RentalTransaction>,
+    
+    #[account(
+        init_if_needed,
+        payer = renter,
+        seeds = [b"escrow", rental_transaction.key().as_ref()],
+        bump,
+        token::mint = usdc_mint,
+        token::authority = rental_transaction
+    )]
+    pub escrow_token_account: Account<'info, TokenAccount>,
+    
+    #[account(
+        mut,
+        associated_token::mint = usdc_mint,
+        associated_token::authority = renter
+    )]
+    pub renter_token_account: Account<'info, TokenAccount>,
+    
+    /// CHECK: canonical fee clamp PDA for the mint, read as unset until the admin configures it
+    #[account(
+        seeds = [b"fee_clamp", usdc_mint.key().as_ref()],
+        bump
+    )]
+    pub fee_clamp: UncheckedAccount<'info>,
+    
+    #[account(
+        seeds = [b"global_state"],
+        bump = global_state.bump
+    )]
+    pub global_state: Account<'info, GlobalState>,
+    
+    pub usdc_mint: Account<'info, Mint>,
+    
+    #[account(mut)]
+    pub renter: Signer<'info>,
+    
+    pub token_program: Program<'info, Token>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct PayBalance<'info> {
+    #[account(
+        mut,
+        seeds = [b"rental_transaction", &rental_transaction.product_id.to_le_bytes()[..8], &rental_transaction.renter.to_bytes()],
+        bump = rental_transaction.bump
+    )]
+    pub rental_transaction: Account<'info, RentalTransaction>,
+
+    #[account(
+        mut,
+        seeds = [b"escrow", rental_transaction.key().as_ref()],
+        bump = rental_transaction.escrow_bump,
+        token::mint = usdc_mint,
+        token::authority = rental_transaction
+    )]
+    pub escrow_token_account: Account<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        associated_token::mint = usdc_mint,
+        associated_token::authority = renter
+    )]
+    pub renter_token_account: Account<'info, TokenAccount>,
+
+    pub usdc_mint: Account<'info, Mint>,
+    pub renter: Signer<'info>,
+    pub token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+pub struct CancelUnpaidBalance<'info> {
+    #[account(
+        mut,
+        close = renter,
+        seeds = [b"rental_transaction", &rental_transaction.product_id.to_le_bytes()[..8], &rental_transaction.renter.to_bytes()],
+        bump = rental_transaction.bump
+    )]
+    pub rental_transaction: Account<'info, RentalTransaction>,
+
+    #[account(
+        mut,
+        close = renter,
+        seeds = [b"escrow", rental_transaction.key().as_ref()],
+        bump = rental_transaction.escrow_bump,
+        token::mint = usdc_mint,
+        token::authority = rental_transaction
+    )]
+    pub escrow_token_account: Account<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        associated_token::mint = usdc_mint,
+        associated_token::authority = rental_transaction.renter
+    )]
+    pub renter_token_account: Account<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        seeds = [b"treasury", usdc_mint.key().as_ref()],
+        bump,
+        token::mint = usdc_mint,
+        token::authority = global_state
+    )]
+    pub treasury_token_account: Account<'info, TokenAccount>,
+
+    /// CHECK: canonical fee clamp PDA for the mint, read as unset until the admin configures it
+    #[account(
+        seeds = [b"fee_clamp", usdc_mint.key().as_ref()],
+        bump
+    )]
+    pub fee_clamp: UncheckedAccount<'info>,
+
+    #[account(
+        seeds = [b"global_state"],
+        bump = global_state.bump
+    )]
+    pub global_state: Account<'info, GlobalState>,
+
+    #[account(
+        mut,
+        seeds = [b"user_profile", rental_transaction.renter.as_ref()],
+        bump = renter_profile.bump
+    )]
+    pub renter_profile: Account<'info, UserProfile>,
+
+    /// CHECK: receives the closed accounts' rent; must be the renter
+    #[account(mut, address = rental_transaction.renter)]
+    pub renter: UncheckedAccount<'info>,
+
+    pub caller: Signer<'info>,
+    pub usdc_mint: Account<'info, Mint>,
+    pub token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+pub struct PayInstallment<'info> {
+    #[account(
+        mut,
+        seeds = [b"rental_transaction", &rental_transaction.product_id.to_le_bytes()[..8], &rental_transaction.renter.to_bytes()],
+        bump = rental_transaction.bump
+    )]
+    pub rental_transaction: Account<'info, RentalTransaction>,
+
+    #[account(
+        mut,
+        seeds = [b"escrow", rental_transaction.key().as_ref()],
+        bump = rental_transaction.escrow_bump,
+        token::mint = usdc_mint,
+        token::authority = rental_transaction
+    )]
+    pub escrow_token_account: Account<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        associated_token::mint = usdc_mint,
+        associated_token::authority = renter
+    )]
+    pub renter_token_account: Account<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        associated_token::mint = usdc_mint,
+        associated_token::authority = rental_transaction.owner_wallet
+    )]
+    pub owner_token_account: Account<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        seeds = [b"treasury", usdc_mint.key().as_ref()],
+        bump,
+        token::mint = usdc_mint,
+        token::authority = global_state
+    )]
+    pub treasury_token_account: Account<'info, TokenAccount>,
+
+    #[account(
+        seeds = [b"user_profile", rental_transaction.owner_wallet.as_ref()],
+        bump = owner_profile.bump
+    )]
+    pub owner_profile: Account<'info, UserProfile>,
+
+    #[account(
+        mut,
+        seeds = [b"referrer", referrer_account.wallet.as_ref()],
+        bump = referrer_account.bump
+    )]
+    pub referrer_account: Option<Account<'info, Referrer>>,
+
+    #[account(mut)]
+    pub referrer_token_account: Option<Account<'info, TokenAccount>>,
+
+    /// CHECK: canonical fee tier PDA, read as unset until the admin configures tiers
+    #[account(
+        seeds = [b"fee_tiers"],
+        bump
+    )]
+    pub fee_tiers: UncheckedAccount<'info>,
+
+    /// CHECK: category fee PDA for the rental's category; required when the rental has one
+    #[account(
+        seeds = [b"category_fee", &rental_transaction.category_id.unwrap_or_default().to_le_bytes()[..8]],
+        bump
+    )]
+    pub category_fee: Option<UncheckedAccount<'info>>,
+
+    /// CHECK: canonical fee clamp PDA for the mint, read as unset until the admin configures it
+    #[account(
+        seeds = [b"fee_clamp", usdc_mint.key().as_ref()],
+        bump
+    )]
+    pub fee_clamp: UncheckedAccount<'info>,
+
+    #[account(
+        seeds = [b"global_state"],
+        bump = global_state.bump
+    )]
+    pub global_state: Account<'info, GlobalState>,
+
+    pub usdc_mint: Account<'info, Mint>,
+    pub renter: Signer<'info>,
+    pub token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+pub struct DefaultInstallmentPlan<'info> {
+    #[account(
+        mut,
+        seeds = [b"rental_transaction", &rental_transaction.product_id.to_le_bytes()[..8], &rental_transaction.renter.to_bytes()],
+        bump = rental_transaction.bump
+    )]
+    pub rental_transaction: Account<'info, RentalTransaction>,
+
+    #[account(
+        mut,
+        seeds = [b"escrow", rental_transaction.key().as_ref()],
+        bump = rental_transaction.escrow_bump,
+        token::mint = usdc_mint,
+        token::authority = rental_transaction
+    )]
+    pub escrow_token_account: Account<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        associated_token::mint = usdc_mint,
+        associated_token::authority = rental_transaction.owner_wallet
+    )]
+    pub owner_token_account: Account<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        seeds = [b"treasury", usdc_mint.key().as_ref()],
+        bump,
+        token::mint = usdc_mint,
+        token::authority = global_state
+    )]
+    pub treasury_token_account: Account<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        seeds = [b"user_profile", rental_transaction.renter.as_ref()],
+        bump = renter_profile.bump
+    )]
+    pub renter_profile: Account<'info, UserProfile>,
+
+    #[account(
+        seeds = [b"user_profile", rental_transaction.owner_wallet.as_ref()],
+        bump = owner_profile.bump
+    )]
+    pub owner_profile: Account<'info, UserProfile>,
+
+    #[account(
+        mut,
+        seeds = [b"referrer", referrer_account.wallet.as_ref()],
+        bump = referrer_account.bump
+    )]
+    pub referrer_account: Option<Account<'info, Referrer>>,
+
+    #[account(mut)]
+    pub referrer_token_account: Option<Account<'info, TokenAccount>>,
+
+    /// CHECK: canonical fee tier PDA, read as unset until the admin configures tiers
+    #[account(
+        seeds = [b"fee_tiers"],
+        bump
+    )]
+    pub fee_tiers: UncheckedAccount<'info>,
+
+    /// CHECK: category fee PDA for the rental's category; required when the rental has one
+    #[account(
+        seeds = [b"category_fee", &rental_transaction.category_id.unwrap_or_default().to_le_bytes()[..8]],
+        bump
+    )]
+    pub category_fee: Option<UncheckedAccount<'info>>,
+
+    /// CHECK: canonical fee clamp PDA for the mint, read as unset until the admin configures it
+    #[account(
+        seeds = [b"fee_clamp", usdc_mint.key().as_ref()],
+        bump
+    )]
+    pub fee_clamp: UncheckedAccount<'info>,
+
+    #[account(
+        seeds = [b"global_state"],
+        bump = global_state.bump
+    )]
+    pub global_state: Account<'info, GlobalState>,
+
+    pub usdc_mint: Account<'info, Mint>,
+    pub owner: Signer<'info>,
+    pub token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+pub struct ClaimAccrued<'info> {
+    #[account(
+        mut,
+        seeds = [b"rental_transaction", &rental_transaction.product_id.to_le_bytes()[..8], &rental_transaction.renter.to_bytes()],
+        bump = rental_transaction.bump
+    )]
+    pub rental_transaction: Account<'info, RentalTransaction>,
+
+    #[account(
+        mut,
+        seeds = [b"escrow", rental_transaction.key().as_ref()],
+        bump = rental_transaction.escrow_bump,
+        token::mint = usdc_mint,
+        token::authority = rental_transaction
+    )]
+    pub escrow_token_account: Account<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        associated_token::mint = usdc_mint,
+        associated_token::authority = rental_transaction.owner_wallet
+    )]
+    pub owner_token_account: Account<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        seeds = [b"treasury", usdc_mint.key().as_ref()],
+        bump,
+        token::mint = usdc_mint,
+        token::authority = global_state
+    )]
+    pub treasury_token_account: Account<'info, TokenAccount>,
+
+    #[account(
+        seeds = [b"user_profile", rental_transaction.owner_wallet.as_ref()],
+        bump = owner_profile.bump
+    )]
+    pub owner_profile: Account<'info, UserProfile>,
+
+    #[account(
+        mut,
+        seeds = [b"referrer", referrer_account.wallet.as_ref()],
+        bump = referrer_account.bump
+    )]
+    pub referrer_account: Option<Account<'info, Referrer>>,
+
+    #[account(mut)]
+    pub referrer_token_account: Option<Account<'info, TokenAccount>>,
+
+    /// CHECK: canonical fee tier PDA, read as unset until the admin configures tiers
+    #[account(
+        seeds = [b"fee_tiers"],
+        bump
+    )]
+    pub fee_tiers: UncheckedAccount<'info>,
+
+    /// CHECK: category fee PDA for the rental's category; required when the rental has one
+    #[account(
+        seeds = [b"category_fee", &rental_transaction.category_id.unwrap_or_default().to_le_bytes()[..8]],
+        bump
+    )]
+    pub category_fee: Option<UncheckedAccount<'info>>,
+
+    /// CHECK: canonical fee clamp PDA for the mint, read as unset until the admin configures it
+    #[account(
+        seeds = [b"fee_clamp", usdc_mint.key().as_ref()],
+        bump
+    )]
+    pub fee_clamp: UncheckedAccount<'info>,
+
+    #[account(
+        seeds = [b"global_state"],
+        bump = global_state.bump
+    )]
+    pub global_state: Account<'info, GlobalState>,
+
+    pub usdc_mint: Account<'info, Mint>,
+    pub owner: Signer<'info>,
+    pub token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+pub struct CompleteRental<'info> {
+    #[account(
+        mut,
+        seeds = [b"rental_transaction", &rental_transaction.product_id.to_le_bytes()[..8], &rental_transaction.renter.to_bytes()],
+        bump = rental_transaction.bump
+    )]
+    pub rental_transaction: Account<'info, RentalTransaction>,
+    
+    #[account(
+        mut,
+        seeds = [b"escrow", rental_transaction.key().as_ref()],
+        bump = rental_transaction.escrow_bump,
+        token::mint = usdc_mint,
+        token::authority = rental_transaction
+    )]
+    pub escrow_token_account: Account<'info, TokenAccount>,
+    
+    #[account(
+        mut,
+        associated_token::mint = usdc_mint,
+        associated_token::authority = rental_transaction.owner_wallet
+    )]
+    pub owner_token_account: Account<'info, TokenAccount>,
+    
+    #[account(
+        mut,
+        seeds = [b"treasury", usdc_mint.key().as_ref()],
+        bump,
+        token::mint = usdc_mint,
+        token::authority = global_state
+    )]
+    pub treasury_token_account: Account<'info, TokenAccount>,
+    
+    #[account(
+        mut,
+        seeds = [b"user_profile", rental_transaction.renter.as_ref()],
+        bump = renter_profile.bump
+    )]
+    pub renter_profile: Account<'info, UserProfile>,
+    
+    #[account(
+        mut,
+        seeds = [b"user_profile", rental_transaction.owner_wallet.as_ref()],
+        bump = owner_profile.bump
+    )]
+    pub owner_profile: Account<'info, UserProfile>,
+    
+    #[account(
+        mut,
+        seeds = [b"referrer", referrer_account.wallet.as_ref()],
+        bump = referrer_account.bump
+    )]
+    pub referrer_account: Option<Account<'info, Referrer>>,
+
+    #[account(mut)]
+    pub referrer_token_account: Option<Account<'info, TokenAccount>>,
+    
+    /// CHECK: canonical fee tier PDA, read as unset until the admin configures tiers
+    #[account(
+        seeds = [b"fee_tiers"],
+        bump
+    )]
+    pub fee_tiers: UncheckedAccount<'info>,
+    
+    /// CHECK: category fee PDA for the rental's category; required when the rental has one
+    #[account(
+        seeds = [b"category_fee", &rental_transaction.category_id.unwrap_or_default().to_le_bytes()[..8]],
+        bump
+    )]
+    pub category_fee: Option<UncheckedAccount<'info>>,
+    
+    /// CHECK: canonical fee clamp PDA for the mint, read as unset until the admin configures it
+    #[account(
+        seeds = [b"fee_clamp", usdc_mint.key().as_ref()],
+        bump
+    )]
+    pub fee_clamp: UncheckedAccount<'info>,
+    
+    #[account(
+        seeds = [b"global_state"],
+        bump = global_state.bump
+    )]
+    pub global_state: Account<'info, GlobalState>,
+
+    // Only needed when settling an early return
+    #[account(
+        mut,
+        associated_token::mint = usdc_mint,
+        associated_token::authority = rental_transaction.renter
+    )]
+    pub renter_token_account: Option<Account<'info, TokenAccount>>,
+    
+    pub usdc_mint: Account<'info, Mint>,
+    pub signer: Signer<'info>,
+    pub token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+pub struct MutualSettle<'info> {
+    #[account(
+        mut,
+        seeds = [b"rental_transaction", &rental_transaction.product_id.to_le_bytes()[..8], &rental_transaction.renter.to_bytes()],
+        bump = rental_transaction.bump
+    )]
+    pub rental_transaction: Account<'info, RentalTransaction>,
+
+    #[account(
+        mut,
+        seeds = [b"escrow", rental_transaction.key().as_ref()],
+        bump = rental_transaction.escrow_bump,
+        token::mint = usdc_mint,
+        token::authority = rental_transaction
+    )]
+    pub escrow_token_account: Account<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        associated_token::mint = usdc_mint,
+        associated_token::authority = rental_transaction.owner_wallet
+    )]
+    pub owner_token_account: Account<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        associated_token::mint = usdc_mint,
+        associated_token::authority = rental_transaction.renter
+    )]
+    pub renter_token_account: Account<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        seeds = [b"treasury", usdc_mint.key().as_ref()],
+        bump,
+        token::mint = usdc_mint,
+        token::authority = global_state
+    )]
+    pub treasury_token_account: Account<'info, TokenAccount>,
+
+    #[account(
+        seeds = [b"user_profile", rental_transaction.owner_wallet.as_ref()],
+        bump = owner_profile.bump
+    )]
+    pub owner_profile: Account<'info, UserProfile>,
+
+    /// CHECK: canonical fee tier PDA, read as unset until the admin configures tiers
+    #[account(
+        seeds = [b"fee_tiers"],
+        bump
+    )]
+    pub fee_tiers: UncheckedAccount<'info>,
+
+    /// CHECK: category fee PDA for the rental's category; required when the rental has one
+    #[account(
+        seeds = [b"category_fee", &rental_transaction.category_id.unwrap_or_default().to_le_bytes()[..8]],
+        bump
+    )]
+    pub category_fee: Option<UncheckedAccount<'info>>,
+
+    /// CHECK: canonical fee clamp PDA for the mint, read as unset until the admin configures it
+    #[account(
+        seeds = [b"fee_clamp", usdc_mint.key().as_ref()],
+        bump
+    )]
+    pub fee_clamp: UncheckedAccount<'info>,
+
+    #[account(
+        seeds = [b"global_state"],
+        bump = global_state.bump
+    )]
+    pub global_state: Account<'info, GlobalState>,
+
+    pub usdc_mint: Account<'info, Mint>,
+    pub owner: Signer<'info>,
+    pub renter: Signer<'info>,
+    pub token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+pub struct OwnerRefund<'info> {
+    #[account(
+        mut,
+        seeds = [b"rental_transaction", &rental_transaction.product_id.to_le_bytes()[..8], &rental_transaction.renter.to_bytes()],
+        bump = rental_transaction.bump
+    )]
+    pub rental_transaction: Account<'info, RentalTransaction>,
+
+    #[account(
+        mut,
+        seeds = [b"escrow", rental_transaction.key().as_ref()],
+        bump = rental_transaction.escrow_bump,
+        token::mint = usdc_mint,
+        token::authority = rental_transaction
+    )]
+    pub escrow_token_account: Account<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        associated_token::mint = usdc_mint,
+        associated_token::authority = owner
+    )]
+    pub owner_token_account: Account<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        associated_token::mint = usdc_mint,
+        associated_token::authority = rental_transaction.renter
+    )]
+    pub renter_token_account: Account<'info, TokenAccount>,
+
+    pub usdc_mint: Account<'info, Mint>,
+    pub owner: Signer<'info>,
+    pub token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+pub struct ProposeAmendment<'info> {
+    #[account(
+        seeds = [b"rental_transaction", &rental_transaction.product_id.to_le_bytes()[..8], &rental_transaction.renter.to_bytes()],
+        bump = rental_transaction.bump
+    )]
+    pub rental_transaction: Account<'info, RentalTransaction>,
+
+    #[account(
+        init,
+        payer = owner,
+        space = 8 + 32 + 32 + 2 + 8 + 8 + 8 + 8 + 1,
+        seeds = [b"amendment", rental_transaction.key().as_ref()],
+        bump
+    )]
+    pub amendment: Account<'info, BookingAmendment>,
+
+    #[account(mut)]
+    pub owner: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct AcceptAmendment<'info> {
+    #[account(
+        mut,
+        seeds = [b"rental_transaction", &rental_transaction.product_id.to_le_bytes()[..8], &rental_transaction.renter.to_bytes()],
+        bump = rental_transaction.bump
+    )]
+    pub rental_transaction: Account<'info, RentalTransaction>,
+
+    #[account(
+        mut,
+        close = owner,
+        seeds = [b"amendment", rental_transaction.key().as_ref()],
+        bump = amendment.bump
+    )]
+    pub amendment: Account<'info, BookingAmendment>,
+
+    /// CHECK: the owner's product PDA; amended terms are priced from it once it exists
+    #[account(
+        seeds = [b"product", rental_transaction.owner_wallet.as_ref(), &rental_transaction.product_id.to_le_bytes()[..8]],
+        bump
+    )]
+    pub product: UncheckedAccount<'info>,
+
+    #[account(
+        seeds = [b"global_state"],
+        bump = global_state.bump
+    )]
+    pub global_state: Account<'info, GlobalState>,
+
+    #[account(
+        mut,
+        seeds = [b"user_profile", rental_transaction.owner_wallet.as_ref()],
+        bump = owner_profile.bump
+    )]
+    pub owner_profile: Account<'info, UserProfile>,
+
+    /// CHECK: address is constrained to the instructions sysvar
+    #[account(address = anchor_lang::solana_program::sysvar::instructions::ID)]
+    pub instructions_sysvar: UncheckedAccount<'info>,
+
+    /// CHECK: receives the amendment's rent; must be the owner
+    #[account(mut, address = rental_transaction.owner_wallet)]
+    pub owner: UncheckedAccount<'info>,
+
+    pub renter: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct CancelAmendment<'info> {
+    /// CHECK: the amendment's rental, which may already have been closed
+    #[account(address = amendment.rental_transaction)]
+    pub rental_transaction: UncheckedAccount<'info>,
+
+    #[account(
+        mut,
+        close = owner,
+        seeds = [b"amendment", rental_transaction.key().as_ref()],
+        bump = amendment.bump
+    )]
+    pub amendment: Account<'info, BookingAmendment>,
+
+    /// CHECK: receives the amendment's rent; must be the owner
+    #[account(mut, address = amendment.owner)]
+    pub owner: UncheckedAccount<'info>,
+
+    pub signer: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct ProposeReschedule<'info> {
+    #[account(
+        seeds = [b"rental_transaction", &rental_transaction.product_id.to_le_bytes()[..8], &rental_transaction.renter.to_bytes()],
+        bump = rental_transaction.bump
+    )]
+    pub rental_transaction: Account<'info, RentalTransaction>,
+
+    #[account(
+        init,
+        payer = proposer,
+        space = 8 + 32 + 32 + 32 + 8 + 8 + 8 + 8 + 8 + 8 + 8 + 1,
+        seeds = [b"reschedule", rental_transaction.key().as_ref()],
+        bump
+    )]
+    pub reschedule_proposal: Account<'info, RescheduleProposal>,
+
+    // Holds the renter's prefunded difference apart from the rental's escrow
+    #[account(
+        init,
+        payer = proposer,
+        seeds = [b"reschedule_escrow", reschedule_proposal.key().as_ref()],
+        bump,
+        token::mint = usdc_mint,
+        token::authority = reschedule_proposal
+    )]
+    pub reschedule_escrow_token_account: Account<'info, TokenAccount>,
+
+    // Only needed when the renter proposes a more expensive slot
+    #[account(
+        mut,
+        associated_token::mint = usdc_mint,
+        associated_token::authority = proposer
+    )]
+    pub renter_token_account: Option<Account<'info, TokenAccount>>,
+
+    /// CHECK: canonical fee clamp PDA for the mint, read as unset until the admin configures it
+    #[account(
+        seeds = [b"fee_clamp", usdc_mint.key().as_ref()],
+        bump
+    )]
+    pub fee_clamp: UncheckedAccount<'info>,
+
+    #[account(
+        seeds = [b"global_state"],
+        bump = global_state.bump
+    )]
+    pub global_state: Account<'info, GlobalState>,
+
+    pub usdc_mint: Account<'info, Mint>,
+
+    #[account(mut)]
+    pub proposer: Signer<'info>,
+
+    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct AcceptReschedule<'info> {
+    #[account(
+        mut,
+        seeds = [b"rental_transaction", &rental_transaction.product_id.to_le_bytes()[..8], &rental_transaction.renter.to_bytes()],
+        bump = rental_transaction.bump
+    )]
+    pub rental_transaction: Account<'info, RentalTransaction>,
+
+    #[account(
+        mut,
+        close = proposer,
+        seeds = [b"reschedule", rental_transaction.key().as_ref()],
+        bump = reschedule_proposal.bump
+    )]
+    pub reschedule_proposal: Account<'info, RescheduleProposal>,
+
+    #[account(
+        mut,
+        close = proposer,
+        seeds = [b"reschedule_escrow", reschedule_proposal.key().as_ref()],
+        bump,
+        token::mint = usdc_mint,
+        token::authority = reschedule_proposal
+    )]
+    pub reschedule_escrow_token_account: Account<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        seeds = [b"escrow", rental_transaction.key().as_ref()],
+        bump = rental_transaction.escrow_bump,
+        token::mint = usdc_mint,
+        token::authority = rental_transaction
+    )]
+    pub escrow_token_account: Account<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        associated_token::mint = usdc_mint,
+        associated_token::authority = rental_transaction.renter
+    )]
+    pub renter_token_account: Account<'info, TokenAccount>,
+
+    /// CHECK: the owner's product PDA; new dates are priced from it once it exists
+    #[account(
+        seeds = [b"product", rental_transaction.owner_wallet.as_ref(), &rental_transaction.product_id.to_le_bytes()[..8]],
+        bump
+    )]
+    pub product: UncheckedAccount<'info>,
+
+    #[account(
+        seeds = [b"global_state"],
+        bump = global_state.bump
+    )]
+    pub global_state: Account<'info, GlobalState>,
+
+    #[account(
+        mut,
+        seeds = [b"user_profile", rental_transaction.owner_wallet.as_ref()],
+        bump = owner_profile.bump
+    )]
+    pub owner_profile: Account<'info, UserProfile>,
+
+    /// CHECK: address is constrained to the instructions sysvar
+    #[account(address = anchor_lang::solana_program::sysvar::instructions::ID)]
+    pub instructions_sysvar: UncheckedAccount<'info>,
+
+    /// CHECK: receives the proposal's rent; must be the proposer
+    #[account(mut, address = reschedule_proposal.proposer)]
+    pub proposer: UncheckedAccount<'info>,
+
+    pub usdc_mint: Account<'info, Mint>,
+    pub acceptor: Signer<'info>,
+    pub token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+pub struct CancelReschedule<'info> {
+    /// CHECK: the proposal's rental, which may already have been closed
+    #[account(address = reschedule_proposal.rental_transaction)]
+    pub rental_transaction: UncheckedAccount<'info>,
+
+    #[account(
+        mut,
+        close = proposer,
+        seeds = [b"reschedule", rental_transaction.key().as_ref()],
+        bump = reschedule_proposal.bump
+    )]
+    pub reschedule_proposal: Account<'info, RescheduleProposal>,
+
+    #[account(
+        mut,
+        close = proposer,
+        seeds = [b"reschedule_escrow", reschedule_proposal.key().as_ref()],
+        bump,
+        token::mint = usdc_mint,
+        token::authority = reschedule_proposal
+    )]
+    pub reschedule_escrow_token_account: Account<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        associated_token::mint = usdc_mint,
+        associated_token::authority = reschedule_proposal.renter
+    )]
+    pub renter_token_account: Account<'info, TokenAccount>,
+
+    /// CHECK: receives the proposal's rent; must be the proposer
+    #[account(mut, address = reschedule_proposal.proposer)]
+    pub proposer: UncheckedAccount<'info>,
+
+    pub usdc_mint: Account<'info, Mint>,
+    pub signer: Signer<'info>,
+    pub token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+pub struct RequestEarlyReturn<'info> {
+    #[account(
+        mut,
+        seeds = [b"rental_transaction", &rental_transaction.product_id.to_le_bytes()[..8], &rental_transaction.renter.to_bytes()],
+        bump = rental_transaction.bump
+    )]
+    pub rental_transaction: Account<'info, RentalTransaction>,
+
+    pub renter: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct AdminIntervene<'info> {
+    #[account(
+        mut,
+        seeds = [b"rental_transaction", &rental_transaction.product_id.to_le_bytes()[..8], &rental_transaction.renter.to_bytes()],
+        bump = rental_transaction.bump
+    )]
+    pub rental_transaction: Account<'info, RentalTransaction>,
+    
+    #[account(
+        mut,
+        seeds = [b"escrow", rental_transaction.key().as_ref()],
+        bump = rental_transaction.escrow_bump,
+        token::mint = usdc_mint,
+        token::authority = rental_transaction
+    )]
+    pub escrow_token_account: Account<'info, TokenAccount>,
+    
+    #[account(
+        mut,
+        associated_token::mint = usdc_mint,
+        associated_token::authority = rental_transaction.owner_wallet
+    )]
+    pub owner_token_account: Account<'info, TokenAccount>,
+    
+    #[account(
+        mut,
+        associated_token::mint = usdc_mint,
+        associated_token::authority = rental_transaction.renter
+    )]
+    pub renter_token_account: Account<'info, TokenAccount>,
+    
+    #[account(
+        mut,
+        seeds = [b"treasury", usdc_mint.key().as_ref()],
+        bump,
+        token::mint = usdc_mint,
+        token::authority = global_state
+    )]
+    pub treasury_token_account: Account<'info, TokenAccount>,
+    
+    #[account(
+        mut,
+        seeds = [b"user_profile", rental_transaction.renter.as_ref()],
+        bump = renter_profile.bump
+    )]
+    pub renter_profile: Account<'info, UserProfile>,
+    
+    #[account(
+        mut,
+        seeds = [b"user_profile", rental_transaction.owner_wallet.as_ref()],
+        bump = owner_profile.bump
+    )]
+    pub owner_profile: Account<'info, UserProfile>,
+    
+    #[account(
+        seeds = [b"global_state"],
+        bump = global_state.bump
+    )]
+    pub global_state: Account<'info, GlobalState>,
+    
+    pub usdc_mint: Account<'info, Mint>,
+    pub admin: Signer<'info>,
+    pub token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+pub struct CancelAsRenterCreated<'info> {
+    #[account(
+        mut,
+        close = renter,
+        seeds = [b"rental_transaction", &rental_transaction.product_id.to_le_bytes()[..8], &rental_transaction.renter.to_bytes()],
+        bump = rental_transaction.bump
+    )]
+    pub rental_transaction: Account<'info, RentalTransaction>,
+
+    #[account(
+        mut,
+        seeds = [b"user_profile", rental_transaction.renter.as_ref()],
+        bump = renter_profile.bump
+    )]
+    pub renter_profile: Account<'info, UserProfile>,
+    
+    #[account(mut)]
+    pub renter: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct CancelAsRenterPaid<'info> {
+    #[account(
+        mut,
+        close = renter,
+        seeds = [b"rental_transaction", &rental_transaction.product_id.to_le_bytes()[..8], &rental_transaction.renter.to_bytes()],
+        bump = rental_transaction.bump
+    )]
+    pub rental_transaction: Account<'info, RentalTransaction>,
+    
+    #[account(
+        mut,
+        close = renter,
+        seeds = [b"escrow", rental_transaction.key().as_ref()],
+        bump = rental_transaction.escrow_bump,
+        token::mint = usdc_mint,
+        token::authority = rental_transaction
+    )]
+    pub escrow_token_account: Account<'info, TokenAccount>,
+    
+    #[account(
+        mut,
+        associated_token::mint = usdc_mint,
+        associated_token::authority = renter,
+    )]
+    pub renter_token_account: Account<'info, TokenAccount>,
+    
+    #[account(
+        mut,
+        seeds = [b"treasury", usdc_mint.key().as_ref()],
+        bump,
+        token::mint = usdc_mint,
+        token::authority = global_state
+    )]
+    pub treasury_token_account: Account<'info, TokenAccount>,
+    
+    /// CHECK: canonical fee clamp PDA for the mint, read as unset until the admin configures it
+    #[account(
+        seeds = [b"fee_clamp", usdc_mint.key().as_ref()],
+        bump
+    )]
+    pub fee_clamp: UncheckedAccount<'info>,
+    
+    #[account(
+        seeds = [b"global_state"],
+        bump = global_state.bump
+    )]
+    pub global_state: Account<'info, GlobalState>,
+
+    #[account(
+        mut,
+        seeds = [b"user_profile", rental_transaction.renter.as_ref()],
+        bump = renter_profile.bump
+    )]
+    pub renter_profile: Account<'info, UserProfile>,
+
+    #[account(mut)]
+    pub renter: Signer<'info>,
+    
+    pub usdc_mint: Account<'info, Mint>,
+    pub token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+pub struct CancelAsOwner<'info> {
+    #[account(
+        mut,
+        close = owner,
+        seeds = [b"rental_transaction", &rental_transaction.product_id.to_le_bytes()[..8], &rental_transaction.renter.to_bytes()],
+        bump = rental_transaction.bump
+    )]
+    pub rental_transaction: Account<'info, RentalTransaction>,
+    
+    #[account(
+        mut,
+        close = owner,
+        seeds = [b"escrow", rental_transaction.key().as_ref()],
+        bump = rental_transaction.escrow_bump,
+        token::mint = usdc_mint,
+        token::authority = rental_transaction
+    )]
+    pub escrow_token_account: Account<'info, TokenAccount>,
+    
+    #[account(
+        mut,
+        associated_token::mint = usdc_mint,
+        associated_token::authority = rental_transaction.renter
+    )]
+    pub renter_token_account: Account<'info, TokenAccount>,
+    
+    #[account(
+        mut,
+        seeds = [b"treasury", usdc_mint.key().as_ref()],
+        bump,
+        token::mint = usdc_mint,
+        token::authority = global_state
+    )]
+    pub treasury_token_account: Account<'info, TokenAccount>,
+    
+    #[account(
+        seeds = [b"global_state"],
+        bump = global_state.bump
+    )]
+    pub global_state: Account<'info, GlobalState>,
+    
+    #[account(
+        mut,
+        seeds = [b"user_profile", rental_transaction.owner_wallet.as_ref()],
+        bump = owner_profile.bump
+    )]
+    pub owner_profile: Account<'info, UserProfile>,
+
+    // Required when the cancellation falls inside the penalty window
+    #[account(
+        mut,
+        seeds = [b"owner_bond", rental_transaction.owner_wallet.as_ref()],
+        bump = owner_bond.bump
+    )]
+    pub owner_bond: Option<Account<'info, OwnerBond>>,
+
+    #[account(
+        mut,
+        seeds = [b"bond_vault", rental_transaction.owner_wallet.as_ref()],
+        bump
+    )]
+    pub bond_vault: Option<Account<'info, TokenAccount>>,
+    
+    pub usdc_mint: Account<'info, Mint>,
+    
+    #[account(mut)]
+    pub owner: Signer<'info>,
+    
+    pub token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+pub struct RecordHandover<'info> {
+    #[account(
+        mut,
+        seeds = [b"rental_transaction", &rental_transaction.product_id.to_le_bytes()[..8], &rental_transaction.renter.to_bytes()],
+        bump = rental_transaction.bump
+    )]
+    pub rental_transaction: Account<'info, RentalTransaction>,
+
+    #[account(
+        seeds = [b"global_state"],
+        bump = global_state.bump
+    )]
+    pub global_state: Account<'info, GlobalState>,
+
+    pub signer: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct ContestHandover<'info> {
+    #[account(
+        mut,
+        seeds = [b"rental_transaction", &rental_transaction.product_id.to_le_bytes()[..8], &rental_transaction.renter.to_bytes()],
+        bump = rental_transaction.bump
+    )]
+    pub rental_transaction: Account<'info, RentalTransaction>,
+
+    #[account(
+        mut,
+        seeds = [b"user_profile", signer.key().as_ref()],
+        bump = opener_profile.bump
+    )]
+    pub opener_profile: Account<'info, UserProfile>,
+
+    #[account(
+        seeds = [b"global_state"],
+        bump = global_state.bump
+    )]
+    pub global_state: Account<'info, GlobalState>,
+
+    pub signer: Signer<'info>,
+}
 
 #[derive(Accounts)]
-#[instruction(product_id: u64)]
-pub struct CreateRentalTransaction<'info> {
+pub struct ClaimNoShow<'info> {
+    #[account(
+        mut,
+        seeds = [b"rental_transaction", &rental_transaction.product_id.to_le_bytes()[..8], &rental_transaction.renter.to_bytes()],
+        bump = rental_transaction.bump
+    )]
+    pub rental_transaction: Account<'info, RentalTransaction>,
+
+    #[account(
+        seeds = [b"global_state"],
+        bump = global_state.bump
+    )]
+    pub global_state: Account<'info, GlobalState>,
+
+    pub claimant: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct ContestNoShow<'info> {
+    #[account(
+        mut,
+        seeds = [b"rental_transaction", &rental_transaction.product_id.to_le_bytes()[..8], &rental_transaction.renter.to_bytes()],
+        bump = rental_transaction.bump
+    )]
+    pub rental_transaction: Account<'info, RentalTransaction>,
+
+    #[account(
+        mut,
+        seeds = [b"user_profile", signer.key().as_ref()],
+        bump = opener_profile.bump
+    )]
+    pub opener_profile: Account<'info, UserProfile>,
+
+    #[account(
+        seeds = [b"global_state"],
+        bump = global_state.bump
+    )]
+    pub global_state: Account<'info, GlobalState>,
+
+    pub signer: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct FinalizeNoShow<'info> {
+    #[account(
+        mut,
+        seeds = [b"rental_transaction", &rental_transaction.product_id.to_le_bytes()[..8], &rental_transaction.renter.to_bytes()],
+        bump = rental_transaction.bump
+    )]
+    pub rental_transaction: Box<Account<'info, RentalTransaction>>,
+
+    #[account(
+        mut,
+        seeds = [b"escrow", rental_transaction.key().as_ref()],
+        bump = rental_transaction.escrow_bump,
+        token::mint = usdc_mint,
+        token::authority = rental_transaction
+    )]
+    pub escrow_token_account: Box<Account<'info, TokenAccount>>,
+
+    #[account(
+        mut,
+        associated_token::mint = usdc_mint,
+        associated_token::authority = rental_transaction.renter
+    )]
+    pub renter_token_account: Box<Account<'info, TokenAccount>>,
+
+    #[account(
+        mut,
+        associated_token::mint = usdc_mint,
+        associated_token::authority = rental_transaction.owner_wallet
+    )]
+    pub owner_token_account: Box<Account<'info, TokenAccount>>,
+
+    #[account(
+        mut,
+        seeds = [b"treasury", usdc_mint.key().as_ref()],
+        bump,
+        token::mint = usdc_mint,
+        token::authority = global_state
+    )]
+    pub treasury_token_account: Box<Account<'info, TokenAccount>>,
+
+    #[account(
+        mut,
+        seeds = [b"user_profile", rental_transaction.renter.as_ref()],
+        bump = renter_profile.bump
+    )]
+    pub renter_profile: Box<Account<'info, UserProfile>>,
+
+    #[account(
+        mut,
+        seeds = [b"user_profile", rental_transaction.owner_wallet.as_ref()],
+        bump = owner_profile.bump
+    )]
+    pub owner_profile: Box<Account<'info, UserProfile>>,
+
+    #[account(
+        mut,
+        seeds = [b"referrer", referrer_account.wallet.as_ref()],
+        bump = referrer_account.bump
+    )]
+    pub referrer_account: Option<Account<'info, Referrer>>,
+
+    #[account(mut)]
+    pub referrer_token_account: Option<Account<'info, TokenAccount>>,
+
+    /// CHECK: canonical fee tier PDA, read as unset until the admin configures tiers
+    #[account(
+        seeds = [b"fee_tiers"],
+        bump
+    )]
+    pub fee_tiers: UncheckedAccount<'info>,
+
+    /// CHECK: category fee PDA for the rental's category; required when the rental has one
+    #[account(
+        seeds = [b"category_fee", &rental_transaction.category_id.unwrap_or_default().to_le_bytes()[..8]],
+        bump
+    )]
+    pub category_fee: Option<UncheckedAccount<'info>>,
+
+    /// CHECK: canonical fee clamp PDA for the mint, read as unset until the admin configures it
+    #[account(
+        seeds = [b"fee_clamp", usdc_mint.key().as_ref()],
+        bump
+    )]
+    pub fee_clamp: UncheckedAccount<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"owner_bond", rental_transaction.owner_wallet.as_ref()],
+        bump = owner_bond.bump
+    )]
+    pub owner_bond: Option<Account<'info, OwnerBond>>,
+
+    #[account(
+        mut,
+        seeds = [b"bond_vault", rental_transaction.owner_wallet.as_ref()],
+        bump
+    )]
+    pub bond_vault: Option<Account<'info, TokenAccount>>,
+
+    #[account(
+        seeds = [b"global_state"],
+        bump = global_state.bump
+    )]
+    pub global_state: Box<Account<'info, GlobalState>>,
+
+    pub usdc_mint: Account<'info, Mint>,
+    pub signer: Signer<'info>,
+    pub token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+pub struct DepositBond<'info> {
+    #[account(
+        init_if_needed,
+        payer = owner,
+        space = 8 + 32 + 32 + (1 + 8) + 8 + 1,
+        seeds = [b"owner_bond", owner.key().as_ref()],
+        bump
+    )]
+    pub owner_bond: Account<'info, OwnerBond>,
+
+    #[account(
+        init_if_needed,
+        payer = owner,
+        seeds = [b"bond_vault", owner.key().as_ref()],
+        bump,
+        token::mint = usdc_mint,
+        token::authority = owner_bond
+    )]
+    pub bond_vault: Account<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        associated_token::mint = usdc_mint,
+        associated_token::authority = owner
+    )]
+    pub owner_token_account: Account<'info, TokenAccount>,
+
+    pub usdc_mint: Account<'info, Mint>,
+
+    #[account(mut)]
+    pub owner: Signer<'info>,
+
+    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct RequestBondWithdrawal<'info> {
+    #[account(
+        mut,
+        seeds = [b"owner_bond", owner.key().as_ref()],
+        bump = owner_bond.bump
+    )]
+    pub owner_bond: Account<'info, OwnerBond>,
+
+    #[account(
+        seeds = [b"user_profile", owner.key().as_ref()],
+        bump = owner_profile.bump
+    )]
+    pub owner_profile: Account<'info, UserProfile>,
+
+    #[account(
+        seeds = [b"global_state"],
+        bump = global_state.bump
+    )]
+    pub global_state: Account<'info, GlobalState>,
+
+    pub owner: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct WithdrawBond<'info> {
+    #[account(
+        mut,
+        seeds = [b"owner_bond", owner.key().as_ref()],
+        bump = owner_bond.bump
+    )]
+    pub owner_bond: Account<'info, OwnerBond>,
+
+    #[account(
+        mut,
+        seeds = [b"bond_vault", owner.key().as_ref()],
+        bump,
+        token::mint = usdc_mint,
+        token::authority = owner_bond
+    )]
+    pub bond_vault: Account<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        associated_token::mint = usdc_mint,
+        associated_token::authority = owner
+    )]
+    pub owner_token_account: Account<'info, TokenAccount>,
+
+    #[account(
+        seeds = [b"user_profile", owner.key().as_ref()],
+        bump = owner_profile.bump
+    )]
+    pub owner_profile: Account<'info, UserProfile>,
+
+    #[account(
+        seeds = [b"global_state"],
+        bump = global_state.bump
+    )]
+    pub global_state: Account<'info, GlobalState>,
+
+    pub usdc_mint: Account<'info, Mint>,
+    pub owner: Signer<'info>,
+    pub token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+pub struct OpenDispute<'info> {
+    #[account(
+        mut,
+        seeds = [b"rental_transaction", &rental_transaction.product_id.to_le_bytes()[..8], &rental_transaction.renter.to_bytes()],
+        bump = rental_transaction.bump
+    )]
+    pub rental_transaction: Account<'info, RentalTransaction>,
+
+    #[account(
+        mut,
+        seeds = [b"user_profile", signer.key().as_ref()],
+        bump = opener_profile.bump
+    )]
+    pub opener_profile: Account<'info, UserProfile>,
+
+    pub signer: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SubmitReview<'info> {
+    #[account(
+        seeds = [b"rental_transaction", &rental_transaction.product_id.to_le_bytes()[..8], &rental_transaction.renter.to_bytes()],
+        bump = rental_transaction.bump
+    )]
+    pub rental_transaction: Account<'info, RentalTransaction>,
+
+    #[account(
+        init,
+        payer = reviewer,
+        space = 8 + 32 + 32 + 32 + 1 + 32 + 8 + 1,
+        seeds = [b"review", rental_transaction.key().as_ref(), reviewer.key().as_ref()],
+        bump
+    )]
+    pub review: Account<'info, Review>,
+
+    #[account(
+        mut,
+        seeds = [b"user_profile", reviewee_profile.wallet.as_ref()],
+        bump = reviewee_profile.bump
+    )]
+    pub reviewee_profile: Account<'info, UserProfile>,
+
+    #[account(mut)]
+    pub reviewer: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct SetPayoutSplits<'info> {
+    #[account(
+        mut,
+        seeds = [b"rental_transaction", &rental_transaction.product_id.to_le_bytes()[..8], &rental_transaction.renter.to_bytes()],
+        bump = rental_transaction.bump
+    )]
+    pub rental_transaction: Account<'info, RentalTransaction>,
+
+    pub owner: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(product_id: u64, owner_wallet: Pubkey, nonce: u64)]
+pub struct RedeemOffer<'info> {
+    #[account(
+        init,
+        payer = renter,
+        space = RentalTransaction::SPACE,
+        seeds = [b"rental_transaction", &product_id.to_le_bytes()[..8], &renter.key().to_bytes()],
+        bump
+    )]
+    pub rental_transaction: Box<Account<'info, RentalTransaction>>,
+
+    #[account(
+        init,
+        payer = renter,
+        seeds = [b"escrow", rental_transaction.key().as_ref()],
+        bump,
+        token::mint = usdc_mint,
+        token::authority = rental_transaction
+    )]
+    pub escrow_token_account: Box<Account<'info, TokenAccount>>,
+
     #[account(
         init,
         payer = renter,
-        space = 8 + 8 + 32 + 32 + 8 + 8 + 8 + (4 + 64) + 1 + 8 + (1 + 8) + (1 + 8) + (1 + 4 + 256) + 1 + 1,
-        seeds = [b"rental_transaction", &product_id.to_le_bytes()[..8], &renter.key().to_bytes()],
+        space = 8 + 32 + 8 + 32 + 8 + 1,
+        seeds = [b"offer_nonce", owner_wallet.as_ref(), &nonce.to_le_bytes()],
         bump
     )]
-    pub rental_transaction: Account<'info, RentalTransaction>,
-    
+    pub used_offer_nonce: Account<'info, UsedOfferNonce>,
+
+    #[account(
+        init_if_needed,
+        payer = renter,
+        space = UserProfile::SPACE,
+        seeds = [b"user_profile", renter.key().as_ref()],
+        bump
+    )]
+    pub renter_profile: Box<Account<'info, UserProfile>>,
+
+    #[account(
+        init_if_needed,
+        payer = renter,
+        space = UserProfile::SPACE,
+        seeds = [b"user_profile", owner_wallet.as_ref()],
+        bump
+    )]
+    pub owner_profile: Box<Account<'info, UserProfile>>,
+
+    /// CHECK: the owner's product PDA; its category applies once it exists
+    #[account(
+        seeds = [b"product", owner_wallet.as_ref(), &product_id.to_le_bytes()[..8]],
+        bump
+    )]
+    pub product: UncheckedAccount<'info>,
+
+    #[account(
+        mut,
+        associated_token::mint = usdc_mint,
+        associated_token::authority = renter
+    )]
+    pub renter_token_account: Box<Account<'info, TokenAccount>>,
+
+    /// CHECK: canonical fee clamp PDA for the mint, read as unset until the admin configures it
+    #[account(
+        seeds = [b"fee_clamp", usdc_mint.key().as_ref()],
+        bump
+    )]
+    pub fee_clamp: UncheckedAccount<'info>,
+
+    #[account(
+        seeds = [b"global_state"],
+        bump = global_state.bump
+    )]
+    pub global_state: Box<Account<'info, GlobalState>>,
+
+    /// CHECK: address is constrained to the instructions sysvar
+    #[account(address = anchor_lang::solana_program::sysvar::instructions::ID)]
+    pub instructions_sysvar: UncheckedAccount<'info>,
+
+    pub usdc_mint: Box<Account<'info, Mint>>,
+
     #[account(mut)]
     pub renter: Signer<'info>,
-    
+
+    pub token_program: Program<'info, Token>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
     pub system_program: Program<'info, System>,
 }
 
-#[derive(Accounts)]
-pub struct PayRental<'info> {
-    #[account(
-        mut,
-        seeds = [b"rental_transaction", &rental_transaction.product_id.to_le_bytes()[..8], &rental_transaction.renter.to_bytes()],
-        bump = rental_transaction.bump
-    )]
-    pub rental_transaction: Account<'info, RentalTransaction>,
-    
-    #[account(
-        init_if_needed,
-        payer = renter,
-        seeds = [b"escrow", rental_transaction.key().as_ref()],
-        bump,
-        token::mint = usdc_mint,
-        token::authority = rental_transaction
-    )]
-    pub escrow_token_account: Account<'info, TokenAccount>,
-    
-    #[account(
-        mut,
-        associated_token::mint = usdc_mint,
-        associated_token::authority = renter
-    )]
-    pub renter_token_account: Account<'info, TokenAccount>,
-    
-    pub usdc_mint: Account<'info, Mint>,
-    
-    #[account(mut)]
-    pub renter: Signer<'info>,
-    
-    pub token_program: Program<'info, Token>,
-    pub associated_token_program: Program<'info, AssociatedToken>,
-    pub system_program: Program<'info, System>,
+// Events for Anchor 0.31
+#[event]
+pub struct RentalTransactionCreated {
+    pub product_id: u64,
+    pub renter: Pubkey,
+    pub owner_wallet: Pubkey,
+    pub total_amount: u64,
+    pub booking_id: String,
+}
+
+#[event]
+pub struct RentalPaymentCompleted {
+    pub booking_id: String,
+    pub amount: u64,
+    pub service_fee: u64,
+    pub renter: Pubkey,
+}
+
+#[event]
+pub struct RentalCompleted {
+    pub booking_id: String,
+    pub owner_amount: u64,
+    pub platform_fee: u64,
+    pub platform_fee_rate: u16,
+    pub service_fee: u64,
+    pub completed_by: Pubkey,
+}
+
+#[event]
+pub struct AdminIntervention {
+    pub booking_id: String,
+    pub owner_amount: u64,
+    pub renter_refund: u64,
+    pub platform_fee: u64,
+    pub service_fee_refund: u64,
+    pub reason: String,
+    pub admin: Pubkey,
+}
+
+#[event]
+pub struct RentalCancelledByRenter {
+    pub booking_id: String,
+    pub renter: Pubkey,
+    pub cancelled_at: i64,
+}
+
+#[event]
+pub struct RentalCancelledByOwner {
+    pub booking_id: String,
+    pub owner: Pubkey,
+    pub refund_amount: u64,
+    pub penalty: u64,
+    pub cancelled_at: i64,
+}
+
+#[event]
+pub struct HandoverRecorded {
+    pub booking_id: String,
+    pub recorded_by: Pubkey,
+    pub handed_over_at: i64,
+    pub contest_deadline: i64,
+}
+
+#[event]
+pub struct NoShowClaimed {
+    pub booking_id: String,
+    pub claimant: Pubkey,
+    pub claimed_at: i64,
+    pub contest_deadline: i64,
+}
+
+#[event]
+pub struct NoShowFinalized {
+    pub booking_id: String,
+    pub claimant: Pubkey,
+    pub finalized_by: Pubkey,
+    pub refund_amount: u64,
+    pub owner_amount: u64,
+    pub penalty: u64,
+    pub finalized_at: i64,
+}
+
+#[event]
+pub struct OwnerBondDeposited {
+    pub owner: Pubkey,
+    pub amount: u64,
+    pub balance: u64,
+}
+
+#[event]
+pub struct OwnerBondWithdrawalRequested {
+    pub owner: Pubkey,
+    pub available_at: i64,
+    pub expires_at: i64,
+}
+
+#[event]
+pub struct OwnerBondWithdrawn {
+    pub owner: Pubkey,
+    pub amount: u64,
+    pub balance: u64,
+}
+
+#[event]
+pub struct OwnerBondPenaltyPaid {
+    pub owner: Pubkey,
+    pub amount: u64,
+    pub destination: Pubkey,
+}
+
+#[event]
+pub struct RentalCancelledByRenterPaid {
+    pub booking_id: String,
+    pub renter: Pubkey,
+    pub refund_amount: u64,
+    pub cancellation_fee: u64,
+    pub service_fee_refund: u64,
+    pub cancelled_at: i64,
+}
+
+#[event]
+pub struct DisputeOpened {
+    pub booking_id: String,
+    pub opened_by: Pubkey,
+    pub opened_at: i64,
+}
+
+#[event]
+pub struct ReviewSubmitted {
+    pub booking_id: String,
+    pub reviewer: Pubkey,
+    pub reviewee: Pubkey,
+    pub rating: u8,
+    pub content_hash: [u8; 32],
+}
+
+#[event]
+pub struct TreasuryWithdrawal {
+    pub mint: Pubkey,
+    pub destination: Pubkey,
+    pub amount: u64,
+    pub admin: Pubkey,
+    pub withdrawn_at: i64,
+}
+
+#[event]
+pub struct PayoutSplitsUpdated {
+    pub booking_id: String,
+    pub payout_splits: Vec<PayoutSplit>,
+}
+
+#[event]
+pub struct PayoutDistributed {
+    pub booking_id: String,
+    pub payouts: Vec<PayoutRecipient>,
+}
+
+#[event]
+pub struct ReferrerRegistered {
+    pub wallet: Pubkey,
+    pub registered_at: i64,
+}
+
+#[event]
+pub struct ReferralFeePaid {
+    pub booking_id: String,
+    pub referrer: Pubkey,
+    pub amount: u64,
+    pub total_earned: u64,
+}
+
+#[event]
+pub struct ProductRatesUpdated {
+    pub product_id: u64,
+    pub owner: Pubkey,
+    pub rates: ProductRates,
+}
+
+#[event]
+pub struct OfferRedeemed {
+    pub booking_id: String,
+    pub owner: Pubkey,
+    pub renter: Pubkey,
+    pub nonce: u64,
+    pub total_amount: u64,
+}
+
+#[event]
+pub struct CouponCreated {
+    pub code: String,
+    pub issuer: Pubkey,
+    pub funded_by: DiscountBearer,
+    pub discount: CouponDiscount,
+    pub max_uses: u32,
+    pub expires_at: i64,
+}
+
+#[event]
+pub struct CouponRedeemed {
+    pub booking_id: String,
+    pub code: String,
+    pub renter: Pubkey,
+    pub discount_amount: u64,
+    pub funded_by: DiscountBearer,
+}
+
+#[event]
+pub struct ProductPoliciesUpdated {
+    pub product_id: u64,
+    pub owner: Pubkey,
+    pub policies: ProductPolicies,
+}
+
+#[event]
+pub struct RentalDepositPaid {
+    pub booking_id: String,
+    pub deposit_amount: u64,
+    pub service_fee: u64,
+    pub balance_due: u64,
+    pub balance_due_at: i64,
+    pub renter: Pubkey,
+}
+
+#[event]
+pub struct AmendmentProposed {
+    pub booking_id: String,
+    pub amendment_number: u16,
+    pub total_amount: u64,
+    pub rental_start: i64,
+    pub rental_end: i64,
+    pub proposed_at: i64,
+}
+
+#[event]
+pub struct BookingAmended {
+    pub booking_id: String,
+    pub amendment_number: u16,
+    pub old_total_amount: u64,
+    pub new_total_amount: u64,
+    pub old_start: i64,
+    pub old_end: i64,
+    pub new_start: i64,
+    pub new_end: i64,
+}
+
+#[event]
+pub struct AmendmentCancelled {
+    pub rental_transaction: Pubkey,
+    pub amendment_number: u16,
+    pub cancelled_by: Pubkey,
+}
+
+#[event]
+pub struct RescheduleProposed {
+    pub booking_id: String,
+    pub proposer: Pubkey,
+    pub rental_start: i64,
+    pub rental_end: i64,
+    pub total_amount: u64,
+    pub proposed_at: i64,
+}
+
+#[event]
+pub struct RentalRescheduled {
+    pub booking_id: String,
+    pub old_start: i64,
+    pub old_end: i64,
+    pub new_start: i64,
+    pub new_end: i64,
+    pub old_total_amount: u64,
+    pub new_total_amount: u64,
+    pub proposed_by: Pubkey,
+    pub accepted_by: Pubkey,
+}
+
+#[event]
+pub struct RescheduleCancelled {
+    pub rental_transaction: Pubkey,
+    pub proposer: Pubkey,
+    pub cancelled_by: Pubkey,
+}
+
+#[event]
+pub struct OwnerRefundIssued {
+    pub booking_id: String,
+    pub owner: Pubkey,
+    pub renter: Pubkey,
+    pub amount: u64,
+    pub refunded_amount: u64,
+    pub from_escrow: bool,
+    pub refunded_at: i64,
+}
+
+#[event]
+pub struct RentalSettledByAgreement {
+    pub booking_id: String,
+    pub renter_refund: u64,
+    pub owner_amount: u64,
+    pub platform_fee: u64,
+    pub service_fee_refund: u64,
+    pub settled_at: i64,
+}
+
+#[event]
+pub struct EarlyReturnRequested {
+    pub booking_id: String,
+    pub renter: Pubkey,
+    pub requested_at: i64,
+}
+
+#[event]
+pub struct EarlyReturnConfirmed {
+    pub booking_id: String,
+    pub returned_at: i64,
+    pub refund_amount: u64,
+    pub confirmed_by: Pubkey,
+}
+
+#[event]
+pub struct AccruedEarningsClaimed {
+    pub booking_id: String,
+    pub amount: u64,
+    pub owner_amount: u64,
+    pub platform_fee: u64,
+    pub released_amount: u64,
+    pub claimed_at: i64,
+}
+
+#[event]
+pub struct InstallmentPaid {
+    pub booking_id: String,
+    pub installment_index: u8,
+    pub amount: u64,
+    pub service_fee: u64,
+    pub released_to_owner: u64,
+    pub next_due_at: Option<i64>,
+    pub renter: Pubkey,
+}
+
+#[event]
+pub struct InstallmentPlanDefaulted {
+    pub booking_id: String,
+    pub missed_installment: u8,
+    pub amount_paid: u64,
+    pub owner_amount: u64,
+    pub platform_fee: u64,
+    pub defaulted_at: i64,
+}
+
+#[event]
+pub struct RentalBalanceDefaulted {
+    pub booking_id: String,
+    pub amount_paid: u64,
+    pub refund_amount: u64,
+    pub cancellation_fee: u64,
+    pub triggered_by: Pubkey,
+    pub cancelled_at: i64,
+}
+
+#[account]
+pub struct GlobalState {
+    pub admin: Pubkey,
+    pub platform_fee_rate: u16,
+    pub bump: u8,
+    pub referral_fee_share: u16,
+    pub service_fee_rate: u16,
+    pub quote_signer: Pubkey,
+    pub min_deposit_rate: u16,
+    pub balance_due_period: i64,
+    pub installment_grace_period: i64,
+    pub settlement_fee_mode: SettlementFeeMode,
+    pub owner_penalty_window: i64,
+    pub owner_penalty_rate: u16,
+    pub bond_withdrawal_cooldown: i64,
+    pub no_show_window: i64,
+    pub no_show_contest_window: i64,
+}
+
+impl GlobalState {
+    // Accounts created before the fee, deposit, bond and no-show settings only hold
+    // the admin, the platform fee rate and the bump
+    pub const LEGACY_SPACE: usize = 8 + 32 + 2 + 1;
+    pub const SPACE: usize = Self::LEGACY_SPACE + 2 + 2 + 32 + 2 + 8 + 8 + 1 + 8 + 2 + 8 + 8 + 8;
+
+    // Defaults for every setting after the bump, shared by initialize and the migration
+    pub fn set_defaults(&mut self) {
+        self.referral_fee_share = 0;
+        self.service_fee_rate = 0;
+        self.quote_signer = Pubkey::default();
+        self.min_deposit_rate = 0;
+        self.balance_due_period = 0;
+        self.installment_grace_period = 3 * SECONDS_PER_DAY;
+        self.settlement_fee_mode = SettlementFeeMode::OwnerShare;
+        self.owner_penalty_window = 7 * SECONDS_PER_DAY;
+        self.owner_penalty_rate = 0;
+        self.bond_withdrawal_cooldown = 7 * SECONDS_PER_DAY;
+        self.no_show_window = SECONDS_PER_DAY;
+        self.no_show_contest_window = SECONDS_PER_DAY;
+    }
+
+    // Cancelling inside the penalty window costs the owner a share of the rental price
+    pub fn owner_cancellation_penalty(&self, rental: &RentalTransaction, current_time: i64) -> Result<u64> {
+        let penalty_window_start = rental.rental_start
+            .checked_sub(self.owner_penalty_window)
+            .ok_or(ErrorCode::MathOverflow)?;
+        if current_time <= penalty_window_start {
+            return Ok(0);
+        }
+
+        self.owner_penalty(rental)
+    }
+
+    // The share of the rental price an owner forfeits for failing a booking
+    pub fn owner_penalty(&self, rental: &RentalTransaction) -> Result<u64> {
+        Ok(rental.total_amount
+            .checked_mul(self.owner_penalty_rate as u64)
+            .ok_or(ErrorCode::MathOverflow)?
+            .checked_div(10000)
+            .ok_or(ErrorCode::MathOverflow)?)
+    }
+}
+
+// How the platform fee is charged when a rental is settled by agreement
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq)]
+pub enum SettlementFeeMode {
+    // No platform fee on negotiated settlements
+    Waived,
+    // The host fee rate applies to the owner's share only
+    OwnerShare,
+}
+
+#[account]
+pub struct Product {
+    pub product_id: u64,
+    pub owner: Pubkey,
+    pub rates: ProductRates,
+    pub category_id: Option<u64>,
+    pub policies: ProductPolicies,
+    pub bump: u8,
+}
+
+impl Product {
+    pub fn policies_snapshot(product: Option<&Product>) -> ProductPolicies {
+        product
+            .map(|product| product.policies.clone())
+            .unwrap_or_default()
+    }
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq, Default)]
+pub struct ProductPolicies {
+    pub cancellation_policy: CancellationPolicy,
+    pub early_return: EarlyReturnPolicy,
+    // Share of the price the owner keeps when the renter never picks up
+    pub renter_no_show_owner_share_bps: u16,
+}
+
+impl ProductPolicies {
+    pub const SPACE: usize = 1 + EarlyReturnPolicy::SPACE + 2;
+
+    pub fn validate(&self) -> Result<()> {
+        require!(
+            self.renter_no_show_owner_share_bps <= 10000,
+            ErrorCode::InvalidPercentages
+        );
+        self.early_return.validate()
+    }
+}
+
+// Refund for unused whole days when a rental ends early. The default refunds nothing.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq, Default)]
+pub struct EarlyReturnPolicy {
+    pub unused_day_refund_bps: u16,
+    pub fee: u64,
+}
+
+impl EarlyReturnPolicy {
+    pub const SPACE: usize = 2 + 8;
+
+    pub fn validate(&self) -> Result<()> {
+        require!(self.unused_day_refund_bps <= 10000, ErrorCode::InvalidPercentages);
+        Ok(())
+    }
+
+    pub fn refund_for(&self, rental: &RentalTransaction, returned_at: i64) -> Result<u64> {
+        let duration = rental.rental_end - rental.rental_start;
+        let unused_days = (rental.rental_end - returned_at).max(0) / SECONDS_PER_DAY;
+        if duration <= 0 || unused_days == 0 {
+            return Ok(0);
+        }
+
+        let unused_value = (rental.total_amount as u128)
+            .checked_mul((unused_days * SECONDS_PER_DAY) as u128)
+            .ok_or(ErrorCode::MathOverflow)?
+            .checked_div(duration as u128)
+            .ok_or(ErrorCode::MathOverflow)?;
+        let refund = unused_value
+            .checked_mul(self.unused_day_refund_bps as u128)
+            .ok_or(ErrorCode::MathOverflow)?
+            .checked_div(10000)
+            .ok_or(ErrorCode::MathOverflow)? as u64;
+
+        Ok(refund.saturating_sub(self.fee))
+    }
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq, Default)]
+pub enum CancellationPolicy {
+    // Full refund any time before the rental starts
+    Flexible,
+    // Full refund until one day before the start, 50% after that
+    #[default]
+    Moderate,
+    // Full refund until seven days before the start, 50% until one day before, nothing after
+    Strict,
+}
+
+impl CancellationPolicy {
+    pub fn refund_rate(&self, time_until_start: i64) -> u64 {
+        if time_until_start <= 0 {
+            return 0;
+        }
+
+        match self {
+            CancellationPolicy::Flexible => 10000,
+            CancellationPolicy::Moderate => {
+                if time_until_start >= SECONDS_PER_DAY {
+                    10000
+                } else {
+                    5000
+                }
+            }
+            CancellationPolicy::Strict => {
+                if time_until_start >= 7 * SECONDS_PER_DAY {
+                    10000
+                } else if time_until_start >= SECONDS_PER_DAY {
+                    5000
+                } else {
+                    0
+                }
+            }
+        }
+    }
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq)]
+pub struct ProductRates {
+    pub daily_rate: u64,
+    pub hourly_rate: u64,
+    pub weekly_rate: u64,
+    pub weekly_discount_bps: u16,
+    pub monthly_discount_bps: u16,
+    // 10000 leaves weekend days at the daily rate
+    pub weekend_multiplier_bps: u16,
+}
+
+impl ProductRates {
+    pub fn validate(&self) -> Result<()> {
+        require!(
+            self.daily_rate > 0 || self.hourly_rate > 0,
+            ErrorCode::InvalidProductRates
+        );
+        require!(
+            self.weekly_discount_bps <= 10000 && self.monthly_discount_bps <= 10000,
+            ErrorCode::InvalidPercentages
+        );
+        require!(self.weekend_multiplier_bps > 0, ErrorCode::InvalidProductRates);
+        Ok(())
+    }
+
+    // Whole weeks use the weekly rate when one is set, remaining whole days the
+    // daily rate (with the weekend multiplier on Saturdays and Sundays, UTC), and
+    // any partial day the hourly rate capped at one day. Weekly and monthly
+    // discounts then apply to the subtotal based on the total duration.
+    pub fn quote_price(&self, rental_start: i64, rental_end: i64) -> Result<u64> {
+        let duration = rental_end
+            .checked_sub(rental_start)
+            .ok_or(ErrorCode::MathOverflow)?;
+        require!(
+            duration > 0 && duration <= MAX_RENTAL_DAYS * SECONDS_PER_DAY,
+            ErrorCode::InvalidRentalPeriod
+        );
+
+        let day_rate = if self.daily_rate > 0 {
+            self.daily_rate
+        } else {
+            self.hourly_rate
+                .checked_mul(24)
+                .ok_or(ErrorCode::MathOverflow)?
+        };
+
+        let full_days = duration / SECONDS_PER_DAY;
+        let remainder = duration % SECONDS_PER_DAY;
+        let mut subtotal: u64 = 0;
+        let mut first_daily_day = 0;
+
+        if self.weekly_rate > 0 {
+            let weeks = full_days / 7;
+            subtotal = self.weekly_rate
+                .checked_mul(weeks as u64)
+                .ok_or(ErrorCode::MathOverflow)?;
+            first_daily_day = weeks * 7;
+        }
+
+        for day in first_daily_day..full_days {
+            let day_start = rental_start + day * SECONDS_PER_DAY;
+            let rate = if is_weekend(day_start) {
+                day_rate
+                    .checked_mul(self.weekend_multiplier_bps as u64)
+                    .ok_or(ErrorCode::MathOverflow)?
+                    .checked_div(10000)
+                    .ok_or(ErrorCode::MathOverflow)?
+            } else {
+                day_rate
+            };
+            subtotal = subtotal
+                .checked_add(rate)
+                .ok_or(ErrorCode::MathOverflow)?;
+        }
+
+        if remainder > 0 {
+            let partial_day = if self.hourly_rate > 0 {
+                let hours = (remainder + SECONDS_PER_HOUR - 1) / SECONDS_PER_HOUR;
+                self.hourly_rate
+                    .checked_mul(hours as u64)
+                    .ok_or(ErrorCode::MathOverflow)?
+                    .min(day_rate)
+            } else {
+                day_rate
+            };
+            subtotal = subtotal
+                .checked_add(partial_day)
+                .ok_or(ErrorCode::MathOverflow)?;
+        }
+
+        let discount_bps = if duration >= 30 * SECONDS_PER_DAY {
+            self.monthly_discount_bps
+        } else if duration >= 7 * SECONDS_PER_DAY {
+            self.weekly_discount_bps
+        } else {
+            0
+        };
+        let discount = subtotal
+            .checked_mul(discount_bps as u64)
+            .ok_or(ErrorCode::MathOverflow)?
+            .checked_div(10000)
+            .ok_or(ErrorCode::MathOverflow)?;
+        let price = subtotal
+            .checked_sub(discount)
+            .ok_or(ErrorCode::MathOverflow)?;
+
+        require!(price > 0, ErrorCode::InvalidAmount);
+        Ok(price)
+    }
+}
+
+pub struct PriceQuote {
+    pub product_id: u64,
+    pub owner_wallet: Pubkey,
+    pub total_amount: u64,
+    pub rental_start: i64,
+    pub rental_end: i64,
+    pub category_id: Option<u64>,
+    pub expires_at: i64,
+}
+
+impl PriceQuote {
+    pub const DOMAIN: &'static [u8] = b"kairoria:price_quote";
+
+    pub fn message(&self) -> Vec<u8> {
+        let mut message = Vec::with_capacity(Self::DOMAIN.len() + 8 + 32 + 8 + 8 + 8 + (1 + 8) + 8);
+        message.extend_from_slice(Self::DOMAIN);
+        message.extend_from_slice(&self.product_id.to_le_bytes());
+        message.extend_from_slice(self.owner_wallet.as_ref());
+        message.extend_from_slice(&self.total_amount.to_le_bytes());
+        message.extend_from_slice(&self.rental_start.to_le_bytes());
+        message.extend_from_slice(&self.rental_end.to_le_bytes());
+        // The category sets the platform fee, so it is signed along with the price
+        message.push(self.category_id.is_some() as u8);
+        message.extend_from_slice(&self.category_id.unwrap_or_default().to_le_bytes());
+        message.extend_from_slice(&self.expires_at.to_le_bytes());
+        message
+    }
+}
+
+pub struct OwnerOffer {
+    pub renter: Pubkey,
+    pub owner_wallet: Pubkey,
+    pub product_id: u64,
+    pub total_amount: u64,
+    pub rental_start: i64,
+    pub rental_end: i64,
+    pub expires_at: i64,
+    pub nonce: u64,
+}
+
+impl OwnerOffer {
+    pub const DOMAIN: &'static [u8] = b"kairoria:owner_offer";
+
+    pub fn message(&self) -> Vec<u8> {
+        let mut message = Vec::with_capacity(Self::DOMAIN.len() + 32 + 32 + 8 * 6);
+        message.extend_from_slice(Self::DOMAIN);
+        message.extend_from_slice(self.renter.as_ref());
+        message.extend_from_slice(self.owner_wallet.as_ref());
+        message.extend_from_slice(&self.product_id.to_le_bytes());
+        message.extend_from_slice(&self.total_amount.to_le_bytes());
+        message.extend_from_slice(&self.rental_start.to_le_bytes());
+        message.extend_from_slice(&self.rental_end.to_le_bytes());
+        message.extend_from_slice(&self.expires_at.to_le_bytes());
+        message.extend_from_slice(&self.nonce.to_le_bytes());
+        message
+    }
+}
+
+// Refundable bond an owner posts against late cancellations and no-shows.
+// Funds sit in the `bond_vault` token account owned by this PDA.
+#[account]
+pub struct OwnerBond {
+    pub owner: Pubkey,
+    pub mint: Pubkey,
+    pub withdrawal_requested_at: Option<i64>,
+    pub penalties_paid: u64,
+    pub bump: u8,
+}
+
+#[account]
+pub struct BookingAmendment {
+    pub rental_transaction: Pubkey,
+    pub owner: Pubkey,
+    pub amendment_number: u16,
+    pub total_amount: u64,
+    pub rental_start: i64,
+    pub rental_end: i64,
+    pub proposed_at: i64,
+    pub bump: u8,
+}
+
+impl BookingAmendment {
+    // Only the next amendment to the rental can be accepted, and only on the terms
+    // the renter restated
+    pub fn matches(
+        &self,
+        rental: &RentalTransaction,
+        total_amount: u64,
+        rental_start: i64,
+        rental_end: i64,
+    ) -> bool {
+        self.total_amount == total_amount &&
+        self.rental_start == rental_start &&
+        self.rental_end == rental_end &&
+        rental.amendment_count.checked_add(1) == Some(self.amendment_number)
+    }
+}
+
+#[account]
+pub struct RescheduleProposal {
+    pub rental_transaction: Pubkey,
+    pub proposer: Pubkey,
+    pub renter: Pubkey,
+    pub rental_start: i64,
+    pub rental_end: i64,
+    pub total_amount: u64,
+    pub service_fee: u64,
+    // Price difference the renter escrowed with their own proposal
+    pub prefunded: u64,
+    pub proposed_at: i64,
+    pub expires_at: i64,
+    pub bump: u8,
+}
+
+#[account]
+pub struct UsedOfferNonce {
+    pub owner: Pubkey,
+    pub nonce: u64,
+    pub rental_transaction: Pubkey,
+    pub used_at: i64,
+    pub bump: u8,
+}
+
+#[account]
+pub struct Coupon {
+    pub code: String,
+    pub issuer: Pubkey,
+    pub funded_by: DiscountBearer,
+    pub discount: CouponDiscount,
+    pub max_uses: u32,
+    pub uses: u32,
+    pub per_wallet_limit: u32,
+    pub expires_at: i64,
+    pub product_id: Option<u64>,
+    pub category_id: Option<u64>,
+    pub bump: u8,
+}
+
+impl Coupon {
+    // Returns (renter_price, platform_subsidy, discount_amount); platform-funded discounts
+    // are topped up into escrow from the treasury so the owner still earns the full price
+    pub fn apply_discount(&self, amount: u64) -> Result<(u64, u64, u64)> {
+        let discount_amount = self.discount_for(amount)?;
+        let renter_price = amount
+            .checked_sub(discount_amount)
+            .ok_or(ErrorCode::MathOverflow)?;
+        let platform_subsidy = match self.funded_by {
+            DiscountBearer::Owner => 0,
+            DiscountBearer::Platform => discount_amount,
+        };
+        Ok((renter_price, platform_subsidy, discount_amount))
+    }
+
+    // Owner-funded coupons only apply to the issuing owner's rentals
+    pub fn check_redeemable(
+        &self,
+        rental: &RentalTransaction,
+        wallet_uses: u32,
+        current_time: i64,
+    ) -> Result<()> {
+        require!(current_time <= self.expires_at, ErrorCode::CouponExpired);
+        require!(self.uses < self.max_uses, ErrorCode::CouponExhausted);
+        require!(wallet_uses < self.per_wallet_limit, ErrorCode::CouponExhausted);
+        require!(
+            self.product_id.is_none_or(|id| id == rental.product_id) &&
+            self.category_id.is_none_or(|id| Some(id) == rental.category_id),
+            ErrorCode::CouponNotApplicable
+        );
+        if self.funded_by == DiscountBearer::Owner {
+            require!(self.issuer == rental.owner_wallet, ErrorCode::CouponNotApplicable);
+        }
+        Ok(())
+    }
+
+    pub fn discount_for(&self, amount: u64) -> Result<u64> {
+        let discount = match self.discount {
+            CouponDiscount::Percent { bps } => amount
+                .checked_mul(bps as u64)
+                .ok_or(ErrorCode::MathOverflow)?
+                .checked_div(10000)
+                .ok_or(ErrorCode::MathOverflow)?,
+            CouponDiscount::Fixed { amount: fixed } => fixed,
+        };
+        Ok(discount.min(amount))
+    }
+}
+
+#[account]
+pub struct CouponRedemption {
+    pub coupon: Pubkey,
+    pub renter: Pubkey,
+    pub uses: u32,
+    pub bump: u8,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq)]
+pub enum CouponDiscount {
+    Percent { bps: u16 },
+    Fixed { amount: u64 },
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq)]
+pub enum DiscountBearer {
+    Platform,
+    Owner,
+}
+
+#[account]
+pub struct FeeClampConfig {
+    pub mint: Pubkey,
+    pub min_fee: u64,
+    // Zero means the fee is uncapped
+    pub max_fee: u64,
+    pub bump: u8,
+}
+
+#[account]
+pub struct CategoryFeeConfig {
+    pub category_id: u64,
+    pub platform_fee_rate: u16,
+    pub bump: u8,
+}
+
+#[account]
+pub struct FeeTierConfig {
+    pub tiers: Vec<FeeTier>,
+    pub bump: u8,
+}
+
+impl FeeTierConfig {
+    // Tiers are sorted by ascending min_volume, so the last match is the best one
+    pub fn rate_for_volume(&self, owner_volume: u64) -> Option<u16> {
+        self.tiers
+            .iter()
+            .rev()
+            .find(|tier| owner_volume >= tier.min_volume)
+            .map(|tier| tier.platform_fee_rate)
+    }
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq)]
+pub struct FeeTier {
+    pub min_volume: u64,
+    pub platform_fee_rate: u16,
+}
+
+#[account]
+pub struct Referrer {
+    pub wallet: Pubkey,
+    pub referral_count: u64,
+    pub total_earned: u64,
+    pub registered_at: i64,
+    pub bump: u8,
+}
+
+#[account]
+pub struct TreasuryDestination {
+    pub token_account: Pubkey,
+    pub mint: Pubkey,
+    pub bump: u8,
+}
+
+#[account]
+pub struct RentalTransaction {
+    pub product_id: u64,
+    pub renter: Pubkey,
+    pub owner_wallet: Pubkey,
+    pub total_amount: u64,
+    pub rental_start: i64,
+    pub rental_end: i64,
+    pub booking_id: String,
+    pub status: TransactionStatus,
+    pub created_at: i64,
+    pub paid_at: Option<i64>,
+    pub completed_at: Option<i64>,
+    pub resolution_reason: Option<String>,
+    pub bump: u8,
+    pub escrow_bump: u8,
+    pub dispute_opened_by: Option<Pubkey>,
+    pub payout_splits: Vec<PayoutSplit>,
+    pub referrer: Option<Pubkey>,
+    pub service_fee: u64,
+    pub category_id: Option<u64>,
+    pub coupon: Option<Pubkey>,
+    pub discount_amount: u64,
+    pub discount_bearer: Option<DiscountBearer>,
+    pub amount_paid: u64,
+    pub balance_due_at: Option<i64>,
+    pub policies: ProductPolicies,
+    pub installment_count: u8,
+    pub installments_paid: u8,
+    pub released_amount: u64,
+    // Platform fee already collected on released earnings
+    pub released_fee: u64,
+    pub early_return_requested_at: Option<i64>,
+    // Total voluntarily refunded by the owner, in escrow or after payout
+    pub refunded_amount: u64,
+    pub amendment_count: u16,
+    pub handed_over_at: Option<i64>,
+    pub handover_recorded_by: Option<Pubkey>,
+    pub no_show_claimed_by: Option<Pubkey>,
+    pub no_show_claimed_at: Option<i64>,
+}
+
+pub struct RenterCancellation {
+    pub refund_amount: u64,
+    pub service_fee_refund: u64,
+    pub renter_payout: u64,
+    pub cancellation_fee: u64,
+    pub treasury_payout: u64,
+}
+
+pub struct Settlement {
+    pub renter_refund: u64,
+    pub owner_share: u64,
+    pub service_fee_refund: u64,
+    pub service_fee_retained: u64,
+}
+
+impl RentalTransaction {
+    pub const LEGACY_SPACE: usize = 8 + 8 + 32 + 32 + 8 + 8 + 8 + (4 + 64) + 1 + 8 + (1 + 8) + (1 + 8) + (1 + 4 + 256) + 1 + 1;
+    pub const SPACE: usize = Self::LEGACY_SPACE + (1 + 32) + (4 + MAX_PAYOUT_SPLITS * (32 + 2)) + (1 + 32) + 8 + (1 + 8) + (1 + 32) + 8 + (1 + 1) + 8 + (1 + 8) + ProductPolicies::SPACE + 1 + 1 + 8 + 8 + (1 + 8) + 8 + 2 + (1 + 8) + (1 + 32) + (1 + 32) + (1 + 8);
+
+    // Pre-upgrade rentals were always paid in full and priced under the default policy
+    pub fn set_legacy_defaults(&mut self) {
+        if self.amount_paid == 0 && matches!(self.status, TransactionStatus::Paid | TransactionStatus::Disputed) {
+            self.amount_paid = self.total_amount;
+        }
+        self.policies = ProductPolicies::default();
+    }
+
+    pub fn is_party(&self, wallet: &Pubkey) -> bool {
+        *wallet == self.renter || *wallet == self.owner_wallet
+    }
+
+    pub fn counterparty(&self, wallet: &Pubkey) -> Option<Pubkey> {
+        if *wallet == self.renter {
+            Some(self.owner_wallet)
+        } else if *wallet == self.owner_wallet {
+            Some(self.renter)
+        } else {
+            None
+        }
+    }
+
+    // Installment plans can be disputed mid-schedule; other partial payments cannot
+    pub fn is_disputable(&self) -> bool {
+        self.status == TransactionStatus::Paid
+            || (self.status == TransactionStatus::PartiallyPaid && self.installment_count > 0)
+    }
+
+    pub fn is_reviewable(&self) -> bool {
+        self.status == TransactionStatus::Completed
+            || self.status == TransactionStatus::Resolved
+            || self.status == TransactionStatus::Settled
+    }
+
+    // Returns (referral_fee, treasury_fee); rentals without a referrer keep the whole fee
+    pub fn split_referral_fee(&self, platform_fee: u64, referral_fee_share: u16) -> Result<(u64, u64)> {
+        let referral_fee = if self.referrer.is_some() {
+            platform_fee
+                .checked_mul(referral_fee_share as u64)
+                .ok_or(ErrorCode::MathOverflow)?
+                .checked_div(10000)
+                .ok_or(ErrorCode::MathOverflow)?
+        } else {
+            0
+        };
+        let treasury_fee = platform_fee
+            .checked_sub(referral_fee)
+            .ok_or(ErrorCode::MathOverflow)?;
+        Ok((referral_fee, treasury_fee))
+    }
+
+    pub fn validate_booking(
+        renter: Pubkey,
+        owner_wallet: Pubkey,
+        total_amount: u64,
+        rental_start: i64,
+        rental_end: i64,
+        booking_id: &str,
+    ) -> Result<()> {
+        require!(total_amount > 0, ErrorCode::InvalidAmount);
+        require!(rental_end > rental_start, ErrorCode::InvalidRentalPeriod);
+        require!(booking_id.len() <= 64, ErrorCode::BookingIdTooLong);
+        require!(owner_wallet != renter, ErrorCode::SelfRental);
+        Ok(())
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    pub fn initialize(
+        &mut self,
+        product_id: u64,
+        renter: Pubkey,
+        owner_wallet: Pubkey,
+        total_amount: u64,
+        rental_start: i64,
+        rental_end: i64,
+        booking_id: String,
+        bump: u8,
+    ) -> Result<()> {
+        self.product_id = product_id;
+        self.renter = renter;
+        self.owner_wallet = owner_wallet;
+        self.total_amount = total_amount;
+        self.rental_start = rental_start;
+        self.rental_end = rental_end;
+        self.booking_id = booking_id;
+        self.status = TransactionStatus::Created;
+        self.created_at = Clock::get()?.unix_timestamp;
+        self.bump = bump;
+        Ok(())
+    }
+
+    // Applies the rental's cancellation policy to what the renter has paid and is still in escrow.
+    // The service fee is only refunded on full-refund cancellations, and the
+    // non-refunded amount is a cancellation fee for the treasury, clamped per mint.
+    pub fn renter_cancellation(
+        &self,
+        current_time: i64,
+        fee_clamp: Option<&FeeClampConfig>,
+    ) -> Result<RenterCancellation> {
+        let refund_rate = self.policies.cancellation_policy
+            .refund_rate(self.rental_start - current_time);
+
+        // Earnings already released to the owner are no longer refundable
+        let refundable_amount = self.escrowed_amount()?;
+        let policy_refund = refundable_amount
+            .checked_mul(refund_rate)
+            .ok_or(ErrorCode::MathOverflow)?
+            .checked_div(10000)
+            .ok_or(ErrorCode::MathOverflow)?;
+        let cancellation_fee = refundable_amount
+            .checked_sub(policy_refund)
+            .ok_or(ErrorCode::MathOverflow)?;
+        let cancellation_fee = if cancellation_fee > 0 {
+            clamp_fee(cancellation_fee, refundable_amount, fee_clamp)
+        } else {
+            0
+        };
+        let refund_amount = refundable_amount
+            .checked_sub(cancellation_fee)
+            .ok_or(ErrorCode::MathOverflow)?;
+
+        let service_fee_refund = if refund_rate == 10000 {
+            self.service_fee
+        } else {
+            0
+        };
+        let service_fee_retained = self.service_fee
+            .checked_sub(service_fee_refund)
+            .ok_or(ErrorCode::MathOverflow)?;
+        let (renter_refund_share, subsidy_refund) = self.split_refund(refund_amount)?;
+        let renter_payout = renter_refund_share
+            .checked_add(service_fee_refund)
+            .ok_or(ErrorCode::MathOverflow)?;
+
+        let treasury_payout = cancellation_fee
+            .checked_add(service_fee_retained)
+            .ok_or(ErrorCode::MathOverflow)?
+            .checked_add(subsidy_refund)
+            .ok_or(ErrorCode::MathOverflow)?;
+
+        Ok(RenterCancellation {
+            refund_amount,
+            service_fee_refund,
+            renter_payout,
+            cancellation_fee,
+            treasury_payout,
+        })
+    }
+
+    // Splits what is still held in escrow by an agreed refund percentage; the
+    // service fee is refunded in the same proportion as the rental price
+    pub fn settlement(&self, renter_refund_percentage: u16) -> Result<Settlement> {
+        let unreleased_amount = self.escrowed_amount()?;
+        let renter_refund = unreleased_amount
+            .checked_mul(renter_refund_percentage as u64)
+            .ok_or(ErrorCode::MathOverflow)?
+            .checked_div(10000)
+            .ok_or(ErrorCode::MathOverflow)?;
+        let owner_share = unreleased_amount
+            .checked_sub(renter_refund)
+            .ok_or(ErrorCode::MathOverflow)?;
+
+        let service_fee_refund = self.service_fee
+            .checked_mul(renter_refund_percentage as u64)
+            .ok_or(ErrorCode::MathOverflow)?
+            .checked_div(10000)
+            .ok_or(ErrorCode::MathOverflow)?;
+        let service_fee_retained = self.service_fee
+            .checked_sub(service_fee_refund)
+            .ok_or(ErrorCode::MathOverflow)?;
+
+        Ok(Settlement {
+            renter_refund,
+            owner_share,
+            service_fee_refund,
+            service_fee_retained,
+        })
+    }
+
+    // A finalized no-show settles what is still in escrow. When the owner never handed
+    // over, the renter gets everything back; when the renter never picked up, the
+    // owner keeps the share set by the product's no-show policy and the service fee
+    // is not refunded.
+    pub fn no_show_settlement(&self, claimant: &Pubkey) -> Result<Settlement> {
+        if *claimant == self.renter {
+            return self.settlement(10000);
+        }
+
+        let unreleased_amount = self.escrowed_amount()?;
+        let owner_share = unreleased_amount
+            .checked_mul(self.policies.renter_no_show_owner_share_bps as u64)
+            .ok_or(ErrorCode::MathOverflow)?
+            .checked_div(10000)
+            .ok_or(ErrorCode::MathOverflow)?;
+        let renter_refund = unreleased_amount
+            .checked_sub(owner_share)
+            .ok_or(ErrorCode::MathOverflow)?;
+
+        Ok(Settlement {
+            renter_refund,
+            owner_share,
+            service_fee_refund: 0,
+            service_fee_retained: self.service_fee,
+        })
+    }
+
+    // Rescheduling re-prices a fully paid booking that has not started. Rentals with
+    // a coupon, installments, released earnings or owner refunds are excluded.
+    pub fn require_reschedulable(&self, current_time: i64) -> Result<()> {
+        require!(
+            self.status == TransactionStatus::Paid,
+            ErrorCode::InvalidTransactionStatus
+        );
+        require!(current_time < self.rental_start, ErrorCode::RescheduleNotAllowed);
+        require!(
+            self.coupon.is_none() &&
+            self.installment_count == 0 &&
+            self.released_amount == 0 &&
+            self.refunded_amount == 0,
+            ErrorCode::RescheduleNotAllowed
+        );
+        Ok(())
+    }
+
+    // A handover recorded by the renter is confirmed at once; one recorded by the
+    // owner only once the renter's contest window has closed
+    pub fn is_handover_confirmed(&self, current_time: i64, contest_window: i64) -> Result<bool> {
+        let handed_over_at = match self.handed_over_at {
+            Some(handed_over_at) => handed_over_at,
+            None => return Ok(false),
+        };
+        if self.handover_recorded_by == Some(self.renter) {
+            return Ok(true);
+        }
+        Ok(current_time > handed_over_at
+            .checked_add(contest_window)
+            .ok_or(ErrorCode::MathOverflow)?)
+    }
+
+    // Handover and no-show claims can only be contested by the other party, and only
+    // while the contest window is open
+    pub fn require_contestable_by(
+        &self,
+        signer: &Pubkey,
+        claimed_by: Option<Pubkey>,
+        claimed_at: Option<i64>,
+        contest_window: i64,
+        current_time: i64,
+    ) -> Result<()> {
+        require!(
+            self.is_party(signer) && claimed_by != Some(*signer),
+            ErrorCode::UnauthorizedDispute
+        );
+        let claimed_at = claimed_at.ok_or(ErrorCode::InvalidTransactionStatus)?;
+        require!(
+            current_time <= claimed_at
+                .checked_add(contest_window)
+                .ok_or(ErrorCode::MathOverflow)?,
+            ErrorCode::ContestWindowClosed
+        );
+        Ok(())
+    }
+
+    // A no-show can be claimed once the window after the start has passed
+    // without a recorded handover
+    pub fn require_no_show_claimable(&self, current_time: i64, no_show_window: i64) -> Result<()> {
+        require!(
+            self.status == TransactionStatus::Paid ||
+            self.status == TransactionStatus::PartiallyPaid,
+            ErrorCode::InvalidTransactionStatus
+        );
+        require!(self.handed_over_at.is_none(), ErrorCode::HandoverAlreadyRecorded);
+        require!(
+            current_time >= self.rental_start
+                .checked_add(no_show_window)
+                .ok_or(ErrorCode::MathOverflow)?,
+            ErrorCode::NoShowWindowNotElapsed
+        );
+        Ok(())
+    }
+
+    // The platform fee is charged on everything released so far and only the part not
+    // yet collected is taken now, so the total does not depend on how often earnings
+    // are released.
+    pub fn release_platform_fee(
+        &self,
+        amount: u64,
+        platform_fee_rate: u16,
+        fee_clamp: Option<&FeeClampConfig>,
+    ) -> Result<u64> {
+        let released_total = self.released_amount
+            .checked_add(amount)
+            .ok_or(ErrorCode::MathOverflow)?;
+        let total_fee = released_total
+            .checked_mul(platform_fee_rate as u64)
+            .ok_or(ErrorCode::MathOverflow)?
+            .checked_div(10000)
+            .ok_or(ErrorCode::MathOverflow)?;
+        let total_fee = clamp_fee(total_fee, released_total, fee_clamp);
+        Ok(total_fee.saturating_sub(self.released_fee).min(amount))
+    }
+
+    // What is still held in escrow for the rental price: paid, less earnings released
+    // to the owner and refunds the owner issued from escrow
+    pub fn escrowed_amount(&self) -> Result<u64> {
+        let escrowed_amount = self.amount_paid
+            .checked_sub(self.released_amount)
+            .ok_or(ErrorCode::MathOverflow)?
+            .checked_sub(self.refunded_amount)
+            .ok_or(ErrorCode::MathOverflow)?;
+        Ok(escrowed_amount)
+    }
+
+    // Earnings accrue linearly over the rental period, capped at what has been paid.
+    // Owner refunds come out of the earnings; accrual stops once the renter has asked
+    // to return the item early.
+    pub fn accrued_amount(&self, current_time: i64) -> Result<u64> {
+        let duration = self.rental_end - self.rental_start;
+        let accrued_until = self.early_return_requested_at
+            .map_or(current_time, |returned_at| current_time.min(returned_at));
+        let elapsed = accrued_until.clamp(self.rental_start, self.rental_end) - self.rental_start;
+        if duration <= 0 {
+            return Ok(0);
+        }
+
+        let earnings = self.total_amount
+            .checked_sub(self.refunded_amount)
+            .ok_or(ErrorCode::MathOverflow)?;
+        let accrued = (earnings as u128)
+            .checked_mul(elapsed as u128)
+            .ok_or(ErrorCode::MathOverflow)?
+            .checked_div(duration as u128)
+            .ok_or(ErrorCode::MathOverflow)? as u64;
+
+        let paid_earnings = self.amount_paid
+            .checked_sub(self.refunded_amount)
+            .ok_or(ErrorCode::MathOverflow)?;
+        Ok(accrued.min(paid_earnings))
+    }
+
+    // Installments split the price evenly, with rounding dust on the last one
+    pub fn installment_amount(&self, index: u8) -> Result<u64> {
+        let count = self.installment_count as u64;
+        let base_amount = self.total_amount
+            .checked_div(count)
+            .ok_or(ErrorCode::MathOverflow)?;
+
+        if index + 1 == self.installment_count {
+            Ok(self.total_amount
+                .checked_sub(base_amount * (count - 1))
+                .ok_or(ErrorCode::MathOverflow)?)
+        } else {
+            Ok(base_amount)
+        }
+    }
+
+    // Installment `index` is due at the start of the period it pays for
+    pub fn installment_due_at(&self, index: u8) -> Result<i64> {
+        let interval = (self.rental_end - self.rental_start)
+            .checked_div(self.installment_count as i64)
+            .ok_or(ErrorCode::MathOverflow)?;
+
+        Ok(self.rental_start
+            .checked_add(interval * index as i64)
+            .ok_or(ErrorCode::MathOverflow)?)
+    }
+
+    // Total of the paid installments whose period has ended; installment `i`
+    // covers the period up to the next installment's due date
+    pub fn ended_installments_total(&self, current_time: i64) -> Result<u64> {
+        let mut total: u64 = 0;
+        for index in 0..self.installments_paid {
+            let period_end = if index + 1 == self.installment_count {
+                self.rental_end
+            } else {
+                self.installment_due_at(index + 1)?
+            };
+            if current_time < period_end {
+                break;
+            }
+            total = total
+                .checked_add(self.installment_amount(index)?)
+                .ok_or(ErrorCode::MathOverflow)?;
+        }
+
+        Ok(total)
+    }
+
+    // Refunds are paid out of escrow pro rata: the platform-funded share of the
+    // price goes back to the treasury and the rest to the renter
+    pub fn split_refund(&self, refund_amount: u64) -> Result<(u64, u64)> {
+        let platform_subsidy = match self.discount_bearer {
+            Some(DiscountBearer::Platform) => self.discount_amount,
+            _ => 0,
+        };
+        if platform_subsidy == 0 || self.total_amount == 0 {
+            return Ok((refund_amount, 0));
+        }
+
+        let subsidy_refund = (refund_amount as u128)
+            .checked_mul(platform_subsidy as u128)
+            .ok_or(ErrorCode::MathOverflow)?
+            .checked_div(self.total_amount as u128)
+            .ok_or(ErrorCode::MathOverflow)? as u64;
+        let renter_refund = refund_amount
+            .checked_sub(subsidy_refund)
+            .ok_or(ErrorCode::MathOverflow)?;
+
+        Ok((renter_refund, subsidy_refund))
+    }
+
+    // Splits are floored per recipient; rounding dust stays with the owner
+    pub fn split_owner_proceeds(&self, owner_amount: u64) -> Result<(Vec<u64>, u64)> {
+        let mut split_amounts = Vec::with_capacity(self.payout_splits.len());
+        let mut owner_remainder = owner_amount;
+
+        for split in self.payout_splits.iter() {
+            let split_amount = owner_amount
+                .checked_mul(split.bps as u64)
+                .ok_or(ErrorCode::MathOverflow)?
+                .checked_div(10000)
+                .ok_or(ErrorCode::MathOverflow)?;
+            owner_remainder = owner_remainder
+                .checked_sub(split_amount)
+                .ok_or(ErrorCode::MathOverflow)?;
+            split_amounts.push(split_amount);
+        }
+
+        Ok((split_amounts, owner_remainder))
+    }
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq)]
+pub struct PayoutSplit {
+    pub recipient: Pubkey,
+    pub bps: u16,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq)]
+pub struct PayoutRecipient {
+    pub recipient: Pubkey,
+    pub amount: u64,
+    pub kind: PayoutKind,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq)]
+pub enum PayoutKind {
+    Owner,
+    Split,
+    Renter,
+    Referrer,
+    Platform,
+}
+
+#[account]
+pub struct UserProfile {
+    pub wallet: Pubkey,
+    pub rentals_completed_as_renter: u64,
+    pub rentals_completed_as_owner: u64,
+    pub cancellations: u64,
+    pub disputes_opened: u64,
+    pub disputes_lost: u64,
+    pub total_volume: u64,
+    pub owner_volume: u64,
+    pub reviews_received: u64,
+    pub rating_total: u64,
+    pub bump: u8,
+    // End of the owner's latest booking that a bond penalty can still apply to
+    pub bond_locked_until: i64,
+}
+
+impl UserProfile {
+    // Profiles created before owner bonds end at the bump
+    pub const LEGACY_SPACE: usize = 8 + 32 + 8 + 8 + 8 + 8 + 8 + 8 + 8 + 8 + 8 + 1;
+    pub const SPACE: usize = Self::LEGACY_SPACE + 8;
+
+    pub fn init_if_empty(&mut self, wallet: Pubkey, bump: u8) {
+        if self.wallet == Pubkey::default() {
+            self.wallet = wallet;
+            self.bump = bump;
+        }
+    }
+
+    pub fn record_cancellation(&mut self) -> Result<()> {
+        self.cancellations = self.cancellations
+            .checked_add(1)
+            .ok_or(ErrorCode::MathOverflow)?;
+        Ok(())
+    }
+
+    // The owner's bond stays locked until every booking it backs has started
+    pub fn lock_bond_until(&mut self, rental_start: i64) {
+        self.bond_locked_until = self.bond_locked_until.max(rental_start);
+    }
+}
+
+#[account]
+pub struct Review {
+    pub rental_transaction: Pubkey,
+    pub reviewer: Pubkey,
+    pub reviewee: Pubkey,
+    pub rating: u8,
+    pub content_hash: [u8; 32],
+    pub created_at: i64,
+    pub bump: u8,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq)]
+pub enum TransactionStatus {
+    Created,
+    Paid,
+    Completed,
+    Cancelled,
+    Resolved,
+    Disputed,
+    PartiallyPaid,
+    Settled,
+    NoShowClaimed,
+}
+
+// Config, product and rental PDAs are passed at their canonical address so callers
+// cannot opt out of them; an address that is uninitialized or closed reads as unset
+fn load_config<T: AccountDeserialize + Owner>(account: &AccountInfo) -> Result<Option<T>> {
+    if account.data_is_empty() {
+        return Ok(None);
+    }
+    require_keys_eq!(*account.owner, T::owner(), ErrorCode::InvalidConfigAccount);
+    let data = account.try_borrow_data()?;
+    Ok(Some(T::try_deserialize(&mut &data[..])?))
+}
+
+// A rental with a category must pass that category's fee PDA so the category rate
+// cannot be skipped
+fn load_category_fee(
+    rental: &RentalTransaction,
+    category_fee: Option<&UncheckedAccount>,
+) -> Result<Option<CategoryFeeConfig>> {
+    match (rental.category_id, category_fee) {
+        (None, _) => Ok(None),
+        (Some(_), None) => err!(ErrorCode::CategoryFeeAccountMissing),
+        (Some(_), Some(category_fee)) => load_config(category_fee),
+    }
+}
+
+// Checks that the instruction immediately before this one is an ed25519 program
+// verification of `expected_message` signed by `expected_signer`, with all
+// signature data carried inline in that instruction.
+fn verify_ed25519_instruction(
+    instructions_sysvar: &AccountInfo,
+    expected_signer: &Pubkey,
+    expected_message: &[u8],
+) -> Result<()> {
+    let current_index = load_current_index_checked(instructions_sysvar)?;
+    require!(current_index > 0, ErrorCode::InvalidSignatureInstruction);
+
+    let instruction = load_instruction_at_checked(current_index as usize - 1, instructions_sysvar)?;
+    require!(
+        instruction.program_id == ed25519_program::ID && instruction.accounts.is_empty(),
+        ErrorCode::InvalidSignatureInstruction
+    );
+
+    check_ed25519_data(&instruction.data, expected_signer, expected_message)
+}
+
+// Only single-signature instructions carrying their own key and message are accepted,
+// so the signed bytes cannot be pointed at another instruction
+fn check_ed25519_data(data: &[u8], expected_signer: &Pubkey, expected_message: &[u8]) -> Result<()> {
+    require!(data.len() >= 16 && data[0] == 1, ErrorCode::InvalidSignatureInstruction);
+
+    let read_u16 = |offset: usize| u16::from_le_bytes([data[offset], data[offset + 1]]);
+    let signature_instruction_index = read_u16(4);
+    let public_key_offset = read_u16(6) as usize;
+    let public_key_instruction_index = read_u16(8);
+    let message_data_offset = read_u16(10) as usize;
+    let message_data_size = read_u16(12) as usize;
+    let message_instruction_index = read_u16(14);
+
+    require!(
+        signature_instruction_index == u16::MAX &&
+        public_key_instruction_index == u16::MAX &&
+        message_instruction_index == u16::MAX,
+        ErrorCode::InvalidSignatureInstruction
+    );
+
+    let public_key = data
+        .get(public_key_offset..public_key_offset + 32)
+        .ok_or(ErrorCode::InvalidSignatureInstruction)?;
+    let message = data
+        .get(message_data_offset..message_data_offset + message_data_size)
+        .ok_or(ErrorCode::InvalidSignatureInstruction)?;
+
+    require!(public_key == expected_signer.as_ref(), ErrorCode::InvalidSignatureSigner);
+    require!(message == expected_message, ErrorCode::InvalidSignatureMessage);
+
+    Ok(())
 }
 
-#[derive(Accounts)]
-pub struct CompleteRental<'info> {
-    #[account(
-        mut,
-        seeds = [b"rental_transaction", &rental_transaction.product_id.to_le_bytes()[..8], &rental_transaction.renter.to_bytes()],
-        bump = rental_transaction.bump
-    )]
-    pub rental_transaction: Account<'info, RentalTransaction>,
-    
-    #[account(
-        mut,
-        seeds = [b"escrow", rental_transaction.key().as_ref()],
-        bump = rental_transaction.escrow_bump,
-        token::mint = usdc_mint,
-        token::authority = rental_transaction
-    )]
-    pub escrow_token_account: Account<'info, TokenAccount>,
-    
-    #[account(
-        mut,
-        associated_token::mint = usdc_mint,
-        associated_token::authority = rental_transaction.owner_wallet
-    )]
-    pub owner_token_account: Account<'info, TokenAccount>,
-    
-    #[account(
-        mut,
-        associated_token::mint = usdc_mint,
-        associated_token::authority = global_state.admin
-    )]
-    pub admin_token_account: Account<'info, TokenAccount>,
-    
-    #[account(
-        seeds = [b"global_state"],
-        bump = global_state.bump
-    )]
-    pub global_state: Account<'info, GlobalState>,
-    
-    pub usdc_mint: Account<'info, Mint>,
-    pub signer: Signer<'info>,
-    pub token_program: Program<'info, Token>,
+// The Unix epoch fell on a Thursday, so day 0 maps to weekday 4 (Sunday = 0)
+fn is_weekend(timestamp: i64) -> bool {
+    let weekday = (timestamp.div_euclid(SECONDS_PER_DAY) + 4).rem_euclid(7);
+    weekday == 0 || weekday == 6
 }
 
-#[derive(Accounts)]
-pub struct AdminIntervene<'info> {
-    #[account(
-        mut,
-        seeds = [b"rental_transaction", &rental_transaction.product_id.to_le_bytes()[..8], &rental_transaction.renter.to_bytes()],
-        bump = rental_transaction.bump
-    )]
-    pub rental_transaction: Account<'info, RentalTransaction>,
-    
-    #[account(
-        mut,
-        seeds = [b"escrow", rental_transaction.key().as_ref()],
-        bump = rental_transaction.escrow_bump,
-        token::mint = usdc_mint,
-        token::authority = rental_transaction
-    )]
-    pub escrow_token_account: Account<'info, TokenAccount>,
-    
-    #[account(
-        mut,
-        associated_token::mint = usdc_mint,
-        associated_token::authority = rental_transaction.owner_wallet
-    )]
-    pub owner_token_account: Account<'info, TokenAccount>,
-    
-    #[account(
-        mut,
-        associated_token::mint = usdc_mint,
-        associated_token::authority = rental_transaction.renter
-    )]
-    pub renter_token_account: Account<'info, TokenAccount>,
-    
-    #[account(
-        mut,
-        associated_token::mint = usdc_mint,
-        associated_token::authority = global_state.admin
-    )]
-    pub admin_token_account: Account<'info, TokenAccount>,
-    
-    #[account(
-        seeds = [b"global_state"],
-        bump = global_state.bump
-    )]
-    pub global_state: Account<'info, GlobalState>,
-    
-    pub usdc_mint: Account<'info, Mint>,
-    pub admin: Signer<'info>,
-    pub token_program: Program<'info, Token>,
+// Category rates replace the global rate; a matching volume tier can only lower it further
+fn resolve_platform_fee_rate(
+    global_state: &GlobalState,
+    category_fee: Option<&CategoryFeeConfig>,
+    fee_tiers: Option<&FeeTierConfig>,
+    owner_volume: u64,
+) -> u16 {
+    let base_rate = category_fee
+        .map(|category_fee| category_fee.platform_fee_rate)
+        .unwrap_or(global_state.platform_fee_rate);
+
+    match fee_tiers.and_then(|fee_tiers| fee_tiers.rate_for_volume(owner_volume)) {
+        Some(tier_rate) => base_rate.min(tier_rate),
+        None => base_rate,
+    }
 }
 
-#[derive(Accounts)]
-pub struct CancelAsRenterCreated<'info> {
-    #[account(
-        mut,
-        close = renter,
-        seeds = [b"rental_transaction", &rental_transaction.product_id.to_le_bytes()[..8], &rental_transaction.renter.to_bytes()],
-        bump = rental_transaction.bump
-    )]
-    pub rental_transaction: Account<'info, RentalTransaction>,
-    
-    #[account(mut)]
-    pub renter: Signer<'info>,
+// Fees never exceed the amount they are charged on
+fn clamp_fee(fee: u64, base_amount: u64, fee_clamp: Option<&FeeClampConfig>) -> u64 {
+    let clamped = match fee_clamp {
+        Some(fee_clamp) => {
+            let fee = fee.max(fee_clamp.min_fee);
+            if fee_clamp.max_fee > 0 {
+                fee.min(fee_clamp.max_fee)
+            } else {
+                fee
+            }
+        }
+        None => fee,
+    };
+    clamped.min(base_amount)
 }
 
-#[derive(Accounts)]
-pub struct CancelAsRenterPaid<'info> {
-    #[account(
-        mut,
-        close = renter,
-        seeds = [b"rental_transaction", &rental_transaction.product_id.to_le_bytes()[..8], &rental_transaction.renter.to_bytes()],
-        bump = rental_transaction.bump
-    )]
-    pub rental_transaction: Account<'info, RentalTransaction>,
-    
-    #[account(
-        mut,
-        close = renter,
-        seeds = [b"escrow", rental_transaction.key().as_ref()],
-        bump = rental_transaction.escrow_bump,
-        token::mint = usdc_mint,
-        token::authority = rental_transaction
-    )]
-    pub escrow_token_account: Account<'info, TokenAccount>,
-    
-    #[account(
-        mut,
-        associated_token::mint = usdc_mint,
-        associated_token::authority = renter,
-    )]
-    pub renter_token_account: Account<'info, TokenAccount>,
-    
-    #[account(
-        mut,
-        associated_token::mint = usdc_mint,
-        associated_token::authority = global_state.admin
-    )]
-    pub admin_token_account: Account<'info, TokenAccount>,
-    
-    #[account(
-        seeds = [b"global_state"],
-        bump = global_state.bump
-    )]
-    pub global_state: Account<'info, GlobalState>,
+// Products with on-chain rates are priced by the program and the client quote must
+// match; otherwise a backend-signed quote is required once a quote signer is set.
+// Returns the category to record on the rental.
+#[allow(clippy::too_many_arguments)]
+fn verify_booking_price(
+    product: Option<&Product>,
+    global_state: &GlobalState,
+    instructions_sysvar: &AccountInfo,
+    product_id: u64,
+    owner_wallet: Pubkey,
+    total_amount: u64,
+    rental_start: i64,
+    rental_end: i64,
+    category_id: Option<u64>,
+    quote_expires_at: Option<i64>,
+) -> Result<Option<u64>> {
+    if let Some(product) = product {
+        require!(product.owner == owner_wallet, ErrorCode::ProductOwnerMismatch);
+        let quoted_price = product.rates.quote_price(rental_start, rental_end)?;
+        require!(total_amount == quoted_price, ErrorCode::PriceMismatch);
+        return Ok(product.category_id);
+    }
 
-    #[account(mut)]
-    pub renter: Signer<'info>,
-    
-    pub usdc_mint: Account<'info, Mint>,
-    pub token_program: Program<'info, Token>,
+    if global_state.quote_signer != Pubkey::default() {
+        let quote = PriceQuote {
+            product_id,
+            owner_wallet,
+            total_amount,
+            rental_start,
+            rental_end,
+            category_id,
+            expires_at: quote_expires_at.ok_or(ErrorCode::MissingPriceQuote)?,
+        };
+        verify_signed_quote(global_state, instructions_sysvar, &quote)?;
+    }
+
+    Ok(category_id)
 }
 
-#[derive(Accounts)]
-pub struct CancelAsOwner<'info> {
-    #[account(
-        mut,
-        close = owner,
-        seeds = [b"rental_transaction", &rental_transaction.product_id.to_le_bytes()[..8], &rental_transaction.renter.to_bytes()],
-        bump = rental_transaction.bump
-    )]
-    pub rental_transaction: Account<'info, RentalTransaction>,
-    
-    #[account(
-        mut,
-        close = owner,
-        seeds = [b"escrow", rental_transaction.key().as_ref()],
-        bump = rental_transaction.escrow_bump,
-        token::mint = usdc_mint,
-        token::authority = rental_transaction
-    )]
-    pub escrow_token_account: Account<'info, TokenAccount>,
-    
-    #[account(
-        mut,
-        associated_token::mint = usdc_mint,
-        associated_token::authority = rental_transaction.renter
-    )]
-    pub renter_token_account: Account<'info, TokenAccount>,
-    
-    pub usdc_mint: Account<'info, Mint>,
-    
-    #[account(mut)]
-    pub owner: Signer<'info>,
-    
-    pub token_program: Program<'info, Token>,
+// The quote must be unexpired and signed by the backend in a preceding instruction
+fn verify_signed_quote(
+    global_state: &GlobalState,
+    instructions_sysvar: &AccountInfo,
+    quote: &PriceQuote,
+) -> Result<()> {
+    require!(
+        Clock::get()?.unix_timestamp <= quote.expires_at,
+        ErrorCode::PriceQuoteExpired
+    );
+    verify_ed25519_instruction(
+        instructions_sysvar,
+        &global_state.quote_signer,
+        &quote.message(),
+    )
 }
 
-// Events for Anchor 0.31
-#[event]
-pub struct RentalTransactionCreated {
-    pub product_id: u64,
-    pub renter: Pubkey,
-    pub owner_wallet: Pubkey,
-    pub total_amount: u64,
-    pub booking_id: String,
+fn record_referral(
+    referrer_account: Option<&mut Account<Referrer>>,
+    referrer: Option<Pubkey>,
+    renter: Pubkey,
+    owner_wallet: Pubkey,
+) -> Result<()> {
+    let Some(referrer) = referrer else {
+        return Ok(());
+    };
+    require!(
+        referrer != renter && referrer != owner_wallet,
+        ErrorCode::InvalidReferrer
+    );
+
+    let referrer_account = referrer_account.ok_or(ErrorCode::ReferrerNotRegistered)?;
+    require!(referrer_account.wallet == referrer, ErrorCode::InvalidReferrer);
+    referrer_account.referral_count = referrer_account.referral_count
+        .checked_add(1)
+        .ok_or(ErrorCode::MathOverflow)?;
+
+    Ok(())
 }
 
-#[event]
-pub struct RentalPaymentCompleted {
-    pub booking_id: String,
-    pub amount: u64,
-    pub renter: Pubkey,
+fn calculate_service_fee(
+    amount: u64,
+    global_state: &GlobalState,
+    fee_clamp: Option<&FeeClampConfig>,
+) -> Result<u64> {
+    if global_state.service_fee_rate == 0 {
+        return Ok(0);
+    }
+
+    let service_fee = amount
+        .checked_mul(global_state.service_fee_rate as u64)
+        .ok_or(ErrorCode::MathOverflow)?
+        .checked_div(10000)
+        .ok_or(ErrorCode::MathOverflow)?;
+
+    Ok(clamp_fee(service_fee, amount, fee_clamp))
 }
 
-#[event]
-pub struct RentalCompleted {
-    pub booking_id: String,
-    pub owner_amount: u64,
-    pub platform_fee: u64,
-    pub completed_by: Pubkey,
+// Shared by pay_rental and book_and_pay: redeems an optional coupon, escrows the renter
+// price plus the service fee, tops up platform-funded discounts from the treasury and
+// marks the rental paid
+#[allow(clippy::too_many_arguments)]
+fn collect_rental_payment<'info>(
+    rental_transaction: &mut Account<'info, RentalTransaction>,
+    coupon: Option<&mut Account<'info, Coupon>>,
+    coupon_redemption: Option<&mut Account<'info, CouponRedemption>>,
+    coupon_redemption_bump: Option<u8>,
+    renter_token_account: &Account<'info, TokenAccount>,
+    escrow_token_account: &Account<'info, TokenAccount>,
+    escrow_bump: u8,
+    treasury_token_account: Option<&Account<'info, TokenAccount>>,
+    renter: &Signer<'info>,
+    global_state: &Account<'info, GlobalState>,
+    fee_clamp: Option<&FeeClampConfig>,
+    token_program: &Program<'info, Token>,
+) -> Result<()> {
+    let current_time = Clock::get()?.unix_timestamp;
+    let mut renter_price = rental_transaction.total_amount;
+    let mut platform_subsidy = 0;
+
+    if let Some(coupon) = coupon {
+        let coupon_redemption = coupon_redemption.ok_or(ErrorCode::InvalidCoupon)?;
+        coupon.check_redeemable(rental_transaction, coupon_redemption.uses, current_time)?;
+
+        let discount_amount;
+        (renter_price, platform_subsidy, discount_amount) =
+            coupon.apply_discount(rental_transaction.total_amount)?;
+        // Owner-funded discounts lower the rental price itself
+        if coupon.funded_by == DiscountBearer::Owner {
+            rental_transaction.total_amount = renter_price;
+        }
+
+        coupon.uses = coupon.uses
+            .checked_add(1)
+            .ok_or(ErrorCode::MathOverflow)?;
+        coupon_redemption.coupon = coupon.key();
+        coupon_redemption.renter = renter.key();
+        coupon_redemption.uses = coupon_redemption.uses
+            .checked_add(1)
+            .ok_or(ErrorCode::MathOverflow)?;
+        coupon_redemption.bump = coupon_redemption_bump.ok_or(ErrorCode::InvalidCoupon)?;
+
+        rental_transaction.coupon = Some(coupon.key());
+        rental_transaction.discount_amount = discount_amount;
+        rental_transaction.discount_bearer = Some(coupon.funded_by.clone());
+
+        emit!(CouponRedeemed {
+            booking_id: rental_transaction.booking_id.clone(),
+            code: coupon.code.clone(),
+            renter: renter.key(),
+            discount_amount,
+            funded_by: coupon.funded_by.clone(),
+        });
+    }
+
+    // Renter-side service fee is charged on top of the rental price
+    let service_fee = calculate_service_fee(renter_price, global_state, fee_clamp)?;
+    let amount_due = renter_price
+        .checked_add(service_fee)
+        .ok_or(ErrorCode::MathOverflow)?;
+
+    transfer_to_escrow(
+        renter_token_account,
+        escrow_token_account,
+        renter,
+        token_program,
+        amount_due,
+    )?;
+
+    if platform_subsidy > 0 {
+        let treasury_token_account = treasury_token_account.ok_or(ErrorCode::InvalidCoupon)?;
+        transfer_from_treasury(
+            treasury_token_account,
+            escrow_token_account.to_account_info(),
+            global_state,
+            token_program,
+            platform_subsidy,
+        )?;
+    }
+
+    rental_transaction.status = TransactionStatus::Paid;
+    rental_transaction.paid_at = Some(current_time);
+    rental_transaction.escrow_bump = escrow_bump;
+    rental_transaction.service_fee = service_fee;
+    rental_transaction.amount_paid = rental_transaction.total_amount;
+
+    emit!(RentalPaymentCompleted {
+        booking_id: rental_transaction.booking_id.clone(),
+        amount: renter_price,
+        service_fee,
+        renter: renter.key(),
+    });
+
+    Ok(())
 }
 
-#[event]
-pub struct AdminIntervention {
-    pub booking_id: String,
-    pub owner_amount: u64,
-    pub renter_refund: u64,
-    pub platform_fee: u64,
-    pub reason: String,
-    pub admin: Pubkey,
+fn transfer_to_escrow<'info>(
+    renter_token_account: &Account<'info, TokenAccount>,
+    escrow_token_account: &Account<'info, TokenAccount>,
+    renter: &Signer<'info>,
+    token_program: &Program<'info, Token>,
+    amount: u64,
+) -> Result<()> {
+    if amount == 0 {
+        return Ok(());
+    }
+
+    let transfer_accounts = Transfer {
+        from: renter_token_account.to_account_info(),
+        to: escrow_token_account.to_account_info(),
+        authority: renter.to_account_info(),
+    };
+
+    let cpi_ctx = CpiContext::new(
+        token_program.to_account_info(),
+        transfer_accounts,
+    );
+
+    token::transfer(cpi_ctx, amount)
+}
+
+fn transfer_from_treasury<'info>(
+    treasury_token_account: &Account<'info, TokenAccount>,
+    destination: AccountInfo<'info>,
+    global_state: &Account<'info, GlobalState>,
+    token_program: &Program<'info, Token>,
+    amount: u64,
+) -> Result<()> {
+    if amount == 0 {
+        return Ok(());
+    }
+
+    let seeds = &[
+        b"global_state".as_ref(),
+        &[global_state.bump],
+    ];
+    let pda_signer_seeds = &[&seeds[..]];
+
+    let transfer_accounts = Transfer {
+        from: treasury_token_account.to_account_info(),
+        to: destination,
+        authority: global_state.to_account_info(),
+    };
+
+    let cpi_ctx = CpiContext::new_with_signer(
+        token_program.to_account_info(),
+        transfer_accounts,
+        pda_signer_seeds,
+    );
+
+    token::transfer(cpi_ctx, amount)
+}
+
+fn transfer_from_reschedule_escrow<'info>(
+    reschedule_proposal: &Account<'info, RescheduleProposal>,
+    reschedule_escrow_token_account: &Account<'info, TokenAccount>,
+    destination: AccountInfo<'info>,
+    token_program: &Program<'info, Token>,
+    amount: u64,
+) -> Result<()> {
+    if amount == 0 {
+        return Ok(());
+    }
+
+    let seeds = &[
+        b"reschedule".as_ref(),
+        reschedule_proposal.rental_transaction.as_ref(),
+        &[reschedule_proposal.bump],
+    ];
+    let pda_signer_seeds = &[&seeds[..]];
+
+    let transfer_accounts = Transfer {
+        from: reschedule_escrow_token_account.to_account_info(),
+        to: destination,
+        authority: reschedule_proposal.to_account_info(),
+    };
+
+    let cpi_ctx = CpiContext::new_with_signer(
+        token_program.to_account_info(),
+        transfer_accounts,
+        pda_signer_seeds,
+    );
+
+    token::transfer(cpi_ctx, amount)
+}
+
+// Grows a program account to `space`, topping up its rent from the payer first
+fn grow_account<'info>(
+    account: &AccountInfo<'info>,
+    payer: &Signer<'info>,
+    system_program: &Program<'info, System>,
+    space: usize,
+) -> Result<()> {
+    let rent_due = Rent::get()?
+        .minimum_balance(space)
+        .saturating_sub(account.lamports());
+    if rent_due > 0 {
+        anchor_lang::system_program::transfer(
+            CpiContext::new(
+                system_program.to_account_info(),
+                anchor_lang::system_program::Transfer {
+                    from: payer.to_account_info(),
+                    to: account.clone(),
+                },
+            ),
+            rent_due,
+        )?;
+    }
+
+    account.realloc(space, true)?;
+    Ok(())
+}
+
+fn transfer_from_bond<'info>(
+    owner_bond: &Account<'info, OwnerBond>,
+    bond_vault: &Account<'info, TokenAccount>,
+    destination: AccountInfo<'info>,
+    token_program: &Program<'info, Token>,
+    amount: u64,
+) -> Result<()> {
+    if amount == 0 {
+        return Ok(());
+    }
+
+    let seeds = &[
+        b"owner_bond".as_ref(),
+        owner_bond.owner.as_ref(),
+        &[owner_bond.bump],
+    ];
+    let pda_signer_seeds = &[&seeds[..]];
+
+    let transfer_accounts = Transfer {
+        from: bond_vault.to_account_info(),
+        to: destination,
+        authority: owner_bond.to_account_info(),
+    };
+
+    let cpi_ctx = CpiContext::new_with_signer(
+        token_program.to_account_info(),
+        transfer_accounts,
+        pda_signer_seeds,
+    );
+
+    token::transfer(cpi_ctx, amount)
 }
 
-#[event]
-pub struct RentalCancelledByRenter {
-    pub booking_id: String,
-    pub renter: Pubkey,
-    pub cancelled_at: i64,
+// Pays a penalty out of the owner's bond, limited to what the bond still holds
+fn pay_bond_penalty<'info>(
+    owner_bond: &mut Account<'info, OwnerBond>,
+    bond_vault: &Account<'info, TokenAccount>,
+    destination: AccountInfo<'info>,
+    token_program: &Program<'info, Token>,
+    penalty: u64,
+) -> Result<u64> {
+    let amount = penalty.min(bond_vault.amount);
+    let destination_key = destination.key();
+
+    transfer_from_bond(owner_bond, bond_vault, destination, token_program, amount)?;
+
+    owner_bond.penalties_paid = owner_bond.penalties_paid
+        .checked_add(amount)
+        .ok_or(ErrorCode::MathOverflow)?;
+
+    emit!(OwnerBondPenaltyPaid {
+        owner: owner_bond.owner,
+        amount,
+        destination: destination_key,
+    });
+
+    Ok(amount)
 }
 
-#[event]
-pub struct RentalCancelledByOwner {
-    pub booking_id: String,
-    pub owner: Pubkey,
-    pub refund_amount: u64,
-    pub cancelled_at: i64,
+fn transfer_from_escrow<'info>(
+    rental_transaction: &Account<'info, RentalTransaction>,
+    escrow_token_account: &Account<'info, TokenAccount>,
+    destination: AccountInfo<'info>,
+    token_program: &Program<'info, Token>,
+    amount: u64,
+) -> Result<()> {
+    if amount == 0 {
+        return Ok(());
+    }
+
+    let product_id_bytes = rental_transaction.product_id.to_le_bytes();
+    let renter_bytes = rental_transaction.renter.to_bytes();
+    let seeds = &[
+        b"rental_transaction".as_ref(),
+        &product_id_bytes[..8],
+        &renter_bytes,
+        &[rental_transaction.bump],
+    ];
+    let pda_signer_seeds = &[&seeds[..]];
+
+    let transfer_accounts = Transfer {
+        from: escrow_token_account.to_account_info(),
+        to: destination,
+        authority: rental_transaction.to_account_info(),
+    };
+
+    let cpi_ctx = CpiContext::new_with_signer(
+        token_program.to_account_info(),
+        transfer_accounts,
+        pda_signer_seeds,
+    );
+
+    token::transfer(cpi_ctx, amount)
 }
 
-#[event]
-pub struct RentalCancelledByRenterPaid {
-    pub booking_id: String,
-    pub renter: Pubkey,
-    pub refund_amount: u64,
-    pub cancellation_fee: u64,
-    pub cancelled_at: i64,
+// Split token accounts are passed as remaining accounts, in the same order as `payout_splits`
+fn distribute_owner_proceeds<'info>(
+    rental_transaction: &Account<'info, RentalTransaction>,
+    escrow_token_account: &Account<'info, TokenAccount>,
+    owner_token_account: &Account<'info, TokenAccount>,
+    split_token_accounts: &'info [AccountInfo<'info>],
+    token_program: &Program<'info, Token>,
+    owner_amount: u64,
+    payouts: &mut Vec<PayoutRecipient>,
+) -> Result<()> {
+    require!(
+        split_token_accounts.len() == rental_transaction.payout_splits.len(),
+        ErrorCode::InvalidPayoutSplitAccounts
+    );
+
+    let (split_amounts, owner_remainder) = rental_transaction.split_owner_proceeds(owner_amount)?;
+
+    for ((split, split_amount), account_info) in rental_transaction.payout_splits.iter()
+        .zip(split_amounts)
+        .zip(split_token_accounts.iter())
+    {
+        let split_token_account = Account::<TokenAccount>::try_from(account_info)?;
+        require!(
+            split_token_account.owner == split.recipient &&
+            split_token_account.mint == escrow_token_account.mint,
+            ErrorCode::InvalidPayoutSplitAccounts
+        );
+
+        transfer_from_escrow(
+            rental_transaction,
+            escrow_token_account,
+            account_info.clone(),
+            token_program,
+            split_amount,
+        )?;
+        payouts.push(PayoutRecipient {
+            recipient: split.recipient,
+            amount: split_amount,
+            kind: PayoutKind::Split,
+        });
+    }
+
+    transfer_from_escrow(
+        rental_transaction,
+        escrow_token_account,
+        owner_token_account.to_account_info(),
+        token_program,
+        owner_remainder,
+    )?;
+    payouts.push(PayoutRecipient {
+        recipient: rental_transaction.owner_wallet,
+        amount: owner_remainder,
+        kind: PayoutKind::Owner,
+    });
+
+    Ok(())
 }
 
-#[account]
-pub struct GlobalState {
-    pub admin: Pubkey,
-    pub platform_fee_rate: u16,
-    pub bump: u8,
+// Pays out a finished rental: owner proceeds and splits, the referrer's share
+// and the platform's fees, then records the rental on both profiles
+fn settle_rental<'info>(
+    accounts: &mut CompleteRental<'info>,
+    split_token_accounts: &'info [AccountInfo<'info>],
+    renter_refund: u64,
+    completed_by: Pubkey,
+    current_time: i64,
+) -> Result<()> {
+    let global_state = &accounts.global_state;
+    let rental_amount = accounts.rental_transaction.total_amount
+        .checked_sub(renter_refund)
+        .ok_or(ErrorCode::MathOverflow)?;
+    // Anything refunded to the renter is excluded from the rental's volume
+    let total_amount = rental_amount
+        .checked_sub(accounts.rental_transaction.refunded_amount)
+        .ok_or(ErrorCode::MathOverflow)?;
+    // Earnings already released from escrow were settled when they were paid out
+    let settle_amount = accounts.rental_transaction.escrowed_amount()?
+        .checked_sub(renter_refund)
+        .ok_or(ErrorCode::MathOverflow)?;
+
+    let category_fee = load_category_fee(
+        &accounts.rental_transaction,
+        accounts.category_fee.as_ref(),
+    )?;
+    let fee_tiers = load_config::<FeeTierConfig>(&accounts.fee_tiers)?;
+    let platform_fee_rate = resolve_platform_fee_rate(
+        global_state,
+        category_fee.as_ref(),
+        fee_tiers.as_ref(),
+        accounts.owner_profile.owner_volume,
+    );
+    
+    let fee_clamp = load_config::<FeeClampConfig>(&accounts.fee_clamp)?;
+    let platform_fee = accounts.rental_transaction.release_platform_fee(
+        settle_amount,
+        platform_fee_rate,
+        fee_clamp.as_ref(),
+    )?;
+    
+    let owner_amount = settle_amount
+        .checked_sub(platform_fee)
+        .ok_or(ErrorCode::MathOverflow)?;
+
+    let (referral_fee, treasury_fee) = accounts.rental_transaction
+        .split_referral_fee(platform_fee, global_state.referral_fee_share)?;
+    let service_fee = accounts.rental_transaction.service_fee;
+    let treasury_fee = treasury_fee
+        .checked_add(service_fee)
+        .ok_or(ErrorCode::MathOverflow)?;
+
+    let mut payouts = Vec::new();
+
+    // Transfer owner proceeds, split across any configured recipients
+    distribute_owner_proceeds(
+        &accounts.rental_transaction,
+        &accounts.escrow_token_account,
+        &accounts.owner_token_account,
+        split_token_accounts,
+        &accounts.token_program,
+        owner_amount,
+        &mut payouts,
+    )?;
+
+    // Transfer the referrer's share of the platform fee
+    pay_referral_fee(
+        &accounts.rental_transaction,
+        &accounts.escrow_token_account,
+        accounts.referrer_account.as_mut(),
+        accounts.referrer_token_account.as_ref(),
+        &accounts.usdc_mint.key(),
+        &accounts.token_program,
+        referral_fee,
+        &mut payouts,
+    )?;
+
+    // Transfer platform fee and service fee to treasury
+    transfer_from_escrow(
+        &accounts.rental_transaction,
+        &accounts.escrow_token_account,
+        accounts.treasury_token_account.to_account_info(),
+        &accounts.token_program,
+        treasury_fee,
+    )?;
+    payouts.push(PayoutRecipient {
+        recipient: accounts.treasury_token_account.key(),
+        amount: treasury_fee,
+        kind: PayoutKind::Platform,
+    });
+
+    let renter_profile = &mut accounts.renter_profile;
+    renter_profile.rentals_completed_as_renter = renter_profile.rentals_completed_as_renter
+        .checked_add(1)
+        .ok_or(ErrorCode::MathOverflow)?;
+    renter_profile.total_volume = renter_profile.total_volume
+        .checked_add(total_amount)
+        .ok_or(ErrorCode::MathOverflow)?;
+
+    let owner_profile = &mut accounts.owner_profile;
+    owner_profile.rentals_completed_as_owner = owner_profile.rentals_completed_as_owner
+        .checked_add(1)
+        .ok_or(ErrorCode::MathOverflow)?;
+    owner_profile.total_volume = owner_profile.total_volume
+        .checked_add(total_amount)
+        .ok_or(ErrorCode::MathOverflow)?;
+    owner_profile.owner_volume = owner_profile.owner_volume
+        .checked_add(total_amount)
+        .ok_or(ErrorCode::MathOverflow)?;
+
+    let rental_transaction = &mut accounts.rental_transaction;
+    rental_transaction.status = TransactionStatus::Completed;
+    rental_transaction.completed_at = Some(current_time);
+
+    emit!(RentalCompleted {
+        booking_id: rental_transaction.booking_id.clone(),
+        owner_amount,
+        platform_fee,
+        platform_fee_rate,
+        service_fee,
+        completed_by,
+    });
+
+    emit!(PayoutDistributed {
+        booking_id: rental_transaction.booking_id.clone(),
+        payouts,
+    });
+
+    Ok(())
 }
 
-#[account]
-pub struct RentalTransaction {
-    pub product_id: u64,
-    pub renter: Pubkey,
-    pub owner_wallet: Pubkey,
-    pub total_amount: u64,
-    pub rental_start: i64,
-    pub rental_end: i64,
-    pub booking_id: String,
-    pub status: TransactionStatus,
-    pub created_at: i64,
-    pub paid_at: Option<i64>,
-    pub completed_at: Option<i64>,
-    pub resolution_reason: Option<String>,
-    pub bump: u8,
-    pub escrow_bump: u8,
+// Pays the referrer's share of a platform fee out of escrow; rentals without a
+// referrer have nothing to pay
+#[allow(clippy::too_many_arguments)]
+fn pay_referral_fee<'info>(
+    rental_transaction: &Account<'info, RentalTransaction>,
+    escrow_token_account: &Account<'info, TokenAccount>,
+    referrer_account: Option<&mut Account<'info, Referrer>>,
+    referrer_token_account: Option<&Account<'info, TokenAccount>>,
+    usdc_mint: &Pubkey,
+    token_program: &Program<'info, Token>,
+    referral_fee: u64,
+    payouts: &mut Vec<PayoutRecipient>,
+) -> Result<()> {
+    let Some(referrer) = rental_transaction.referrer else {
+        return Ok(());
+    };
+
+    let referrer_account = referrer_account.ok_or(ErrorCode::ReferrerNotRegistered)?;
+    let referrer_token_account = referrer_token_account.ok_or(ErrorCode::InvalidReferrerTokenAccount)?;
+    require!(referrer_account.wallet == referrer, ErrorCode::InvalidReferrer);
+    require!(
+        referrer_token_account.owner == referrer &&
+        referrer_token_account.mint == *usdc_mint,
+        ErrorCode::InvalidReferrerTokenAccount
+    );
+
+    transfer_from_escrow(
+        rental_transaction,
+        escrow_token_account,
+        referrer_token_account.to_account_info(),
+        token_program,
+        referral_fee,
+    )?;
+    referrer_account.total_earned = referrer_account.total_earned
+        .checked_add(referral_fee)
+        .ok_or(ErrorCode::MathOverflow)?;
+    payouts.push(PayoutRecipient {
+        recipient: referrer,
+        amount: referral_fee,
+        kind: PayoutKind::Referrer,
+    });
+
+    emit!(ReferralFeePaid {
+        booking_id: rental_transaction.booking_id.clone(),
+        referrer,
+        amount: referral_fee,
+        total_earned: referrer_account.total_earned,
+    });
+
+    Ok(())
 }
 
-#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq)]
-pub enum TransactionStatus {
-    Created,
-    Paid,
-    Completed,
-    Cancelled,
-    Resolved,
+// Pays out earnings ahead of completion: the platform fee is shared with any
+// referrer and the treasury, and the rest goes to the owner and any payout splits
+#[allow(clippy::too_many_arguments)]
+fn release_owner_earnings<'info>(
+    rental_transaction: &Account<'info, RentalTransaction>,
+    escrow_token_account: &Account<'info, TokenAccount>,
+    owner_token_account: &Account<'info, TokenAccount>,
+    treasury_token_account: &Account<'info, TokenAccount>,
+    referrer_account: Option<&mut Account<'info, Referrer>>,
+    referrer_token_account: Option<&Account<'info, TokenAccount>>,
+    split_token_accounts: &'info [AccountInfo<'info>],
+    token_program: &Program<'info, Token>,
+    amount: u64,
+    platform_fee_rate: u16,
+    referral_fee_share: u16,
+    fee_clamp: Option<&FeeClampConfig>,
+) -> Result<(u64, u64)> {
+    let platform_fee = rental_transaction.release_platform_fee(amount, platform_fee_rate, fee_clamp)?;
+    let owner_amount = amount
+        .checked_sub(platform_fee)
+        .ok_or(ErrorCode::MathOverflow)?;
+
+    let mut payouts = Vec::new();
+    distribute_owner_proceeds(
+        rental_transaction,
+        escrow_token_account,
+        owner_token_account,
+        split_token_accounts,
+        token_program,
+        owner_amount,
+        &mut payouts,
+    )?;
+
+    let (referral_fee, treasury_fee) = rental_transaction
+        .split_referral_fee(platform_fee, referral_fee_share)?;
+    pay_referral_fee(
+        rental_transaction,
+        escrow_token_account,
+        referrer_account,
+        referrer_token_account,
+        &treasury_token_account.mint,
+        token_program,
+        referral_fee,
+        &mut payouts,
+    )?;
+
+    transfer_from_escrow(
+        rental_transaction,
+        escrow_token_account,
+        treasury_token_account.to_account_info(),
+        token_program,
+        treasury_fee,
+    )?;
+
+    Ok((owner_amount, platform_fee))
 }
 
 #[error_code]
@@ -868,4 +7437,946 @@ pub enum ErrorCode {
     MathOverflow,
     #[msg("Cancellation too late - rental period has already started")]
     CancellationTooLate,
+    #[msg("Rental account is already at the current layout")]
+    RentalAlreadyMigrated,
+    #[msg("Account is not a rental transaction")]
+    InvalidRentalAccount,
+    #[msg("Unauthorized dispute - only renter or owner can open a dispute")]
+    UnauthorizedDispute,
+    #[msg("Owner cannot rent their own product")]
+    SelfRental,
+    #[msg("Rating must be between 1 and 5")]
+    InvalidRating,
+    #[msg("Unauthorized review - only renter or owner can review the other party")]
+    UnauthorizedReview,
+    #[msg("Insufficient treasury balance")]
+    InsufficientTreasuryBalance,
+    #[msg("Unauthorized payout splits - only the owner can configure splits")]
+    UnauthorizedPayoutSplits,
+    #[msg("Too many payout split recipients")]
+    TooManyPayoutSplits,
+    #[msg("Invalid payout split recipient")]
+    InvalidPayoutSplit,
+    #[msg("Payout split token accounts do not match configured recipients")]
+    InvalidPayoutSplitAccounts,
+    #[msg("Invalid referrer")]
+    InvalidReferrer,
+    #[msg("Referrer is not registered")]
+    ReferrerNotRegistered,
+    #[msg("Invalid referrer token account")]
+    InvalidReferrerTokenAccount,
+    #[msg("Too many fee tiers")]
+    TooManyFeeTiers,
+    #[msg("Fee tiers must be sorted by strictly increasing volume")]
+    InvalidFeeTiers,
+    #[msg("Account is not owned by this program")]
+    InvalidConfigAccount,
+    #[msg("Category fee config does not match the rental category")]
+    CategoryMismatch,
+    #[msg("Category fee account is required for this rental")]
+    CategoryFeeAccountMissing,
+    #[msg("Maximum fee must be zero or at least the minimum fee")]
+    InvalidFeeClamp,
+    #[msg("Product must have a daily or hourly rate and a positive weekend multiplier")]
+    InvalidProductRates,
+    #[msg("Unauthorized product update - only the product owner can update rates")]
+    UnauthorizedProductUpdate,
+    #[msg("Owner wallet does not match the product owner")]
+    ProductOwnerMismatch,
+    #[msg("Quoted price does not match the on-chain price")]
+    PriceMismatch,
+    #[msg("A signed price quote is required for this booking")]
+    MissingPriceQuote,
+    #[msg("Price quote has expired")]
+    PriceQuoteExpired,
+    #[msg("Missing or malformed ed25519 signature instruction")]
+    InvalidSignatureInstruction,
+    #[msg("Signature was not produced by the expected signer")]
+    InvalidSignatureSigner,
+    #[msg("Signed message does not match the booking")]
+    InvalidSignatureMessage,
+    #[msg("Offer has expired")]
+    OfferExpired,
+    #[msg("Invalid coupon code")]
+    InvalidCouponCode,
+    #[msg("Invalid coupon configuration or accounts")]
+    InvalidCoupon,
+    #[msg("Coupon has expired")]
+    CouponExpired,
+    #[msg("Coupon usage limit reached")]
+    CouponExhausted,
+    #[msg("Coupon does not apply to this rental")]
+    CouponNotApplicable,
+    #[msg("Partial payments are not enabled")]
+    PartialPaymentDisabled,
+    #[msg("Balance payment deadline has passed")]
+    BalanceDeadlinePassed,
+    #[msg("Balance is not overdue yet")]
+    BalanceNotOverdue,
+    #[msg("Invalid number of installments")]
+    InvalidInstallmentCount,
+    #[msg("Installment is not due yet")]
+    InstallmentNotYetDue,
+    #[msg("Installment payment deadline has passed")]
+    InstallmentOverdue,
+    #[msg("Installment is not overdue yet")]
+    InstallmentNotOverdue,
+    #[msg("Only the owner can claim accrued earnings")]
+    UnauthorizedClaim,
+    #[msg("No accrued earnings to claim")]
+    NothingToClaim,
+    #[msg("Only the renter can request, and the owner confirm, an early return")]
+    UnauthorizedEarlyReturn,
+    #[msg("Early return is only possible during the rental period")]
+    EarlyReturnNotAllowed,
+    #[msg("Early return has already been requested")]
+    EarlyReturnAlreadyRequested,
+    #[msg("No early return has been requested")]
+    EarlyReturnNotRequested,
+    #[msg("Invalid renter token account")]
+    InvalidRenterTokenAccount,
+    #[msg("Both the owner and the renter must sign a settlement")]
+    UnauthorizedSettlement,
+    #[msg("Only the owner can issue a voluntary refund")]
+    UnauthorizedOwnerRefund,
+    #[msg("Refund exceeds the amount available")]
+    RefundExceedsAvailable,
+    #[msg("Only the renter or owner can propose, and the other party accept, a reschedule")]
+    UnauthorizedReschedule,
+    #[msg("This rental cannot be rescheduled")]
+    RescheduleNotAllowed,
+    #[msg("Reschedule proposal has expired")]
+    RescheduleExpired,
+    #[msg("Only the owner can propose, and the renter accept, an amendment")]
+    UnauthorizedAmendment,
+    #[msg("Amendment does not match the accepted terms")]
+    AmendmentMismatch,
+    #[msg("An owner bond is required for this action")]
+    OwnerBondRequired,
+    #[msg("No bond withdrawal has been requested")]
+    BondWithdrawalNotRequested,
+    #[msg("Bond withdrawal cooldown has not elapsed")]
+    BondCooldownActive,
+    #[msg("Bond withdrawal request has expired")]
+    BondWithdrawalExpired,
+    #[msg("Global state has already been migrated")]
+    GlobalStateAlreadyMigrated,
+    #[msg("Owner bond is held in a different mint")]
+    InvalidBondMint,
+    #[msg("Owner bond is locked until the owner's upcoming bookings have started")]
+    BondLocked,
+    #[msg("Profile account is already at the current layout")]
+    ProfileAlreadyMigrated,
+    #[msg("Account is not a user profile")]
+    InvalidProfileAccount,
+    #[msg("Only the renter or the owner can record the handover")]
+    UnauthorizedHandover,
+    #[msg("Handover has already been recorded")]
+    HandoverAlreadyRecorded,
+    #[msg("Handover has not been confirmed")]
+    HandoverNotConfirmed,
+    #[msg("Not authorized to claim or finalize this no-show")]
+    UnauthorizedNoShowClaim,
+    #[msg("No-show window has not elapsed")]
+    NoShowWindowNotElapsed,
+    #[msg("Contest window has closed")]
+    ContestWindowClosed,
+    #[msg("No-show contest window is still open")]
+    NoShowContestWindowOpen,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rental() -> RentalTransaction {
+        RentalTransaction {
+            product_id: 7,
+            renter: Pubkey::new_unique(),
+            owner_wallet: Pubkey::new_unique(),
+            total_amount: 1_000_000,
+            rental_start: 10_000,
+            rental_end: 100_000,
+            booking_id: "booking".to_string(),
+            status: TransactionStatus::Paid,
+            created_at: 1_000,
+            paid_at: Some(2_000),
+            completed_at: None,
+            resolution_reason: None,
+            bump: 255,
+            escrow_bump: 254,
+            dispute_opened_by: None,
+            payout_splits: Vec::new(),
+            referrer: None,
+            service_fee: 0,
+            category_id: None,
+            coupon: None,
+            discount_amount: 0,
+            discount_bearer: None,
+            amount_paid: 1_000_000,
+            balance_due_at: None,
+            policies: ProductPolicies::default(),
+            installment_count: 0,
+            installments_paid: 0,
+            released_amount: 0,
+            released_fee: 0,
+            early_return_requested_at: None,
+            refunded_amount: 0,
+            amendment_count: 0,
+            handed_over_at: None,
+            handover_recorded_by: None,
+            no_show_claimed_by: None,
+            no_show_claimed_at: None,
+        }
+    }
+
+    fn global_state() -> GlobalState {
+        GlobalState {
+            admin: Pubkey::new_unique(),
+            platform_fee_rate: 1_000,
+            bump: 255,
+            referral_fee_share: 0,
+            service_fee_rate: 0,
+            quote_signer: Pubkey::new_unique(),
+            min_deposit_rate: 0,
+            balance_due_period: 0,
+            installment_grace_period: 0,
+            settlement_fee_mode: SettlementFeeMode::OwnerShare,
+            owner_penalty_window: 7 * SECONDS_PER_DAY,
+            owner_penalty_rate: 0,
+            bond_withdrawal_cooldown: 0,
+            no_show_window: SECONDS_PER_DAY,
+            no_show_contest_window: SECONDS_PER_DAY,
+        }
+    }
+
+    fn profile() -> UserProfile {
+        UserProfile {
+            wallet: Pubkey::default(),
+            rentals_completed_as_renter: 0,
+            rentals_completed_as_owner: 0,
+            cancellations: 0,
+            disputes_opened: 0,
+            disputes_lost: 0,
+            total_volume: 0,
+            owner_volume: 0,
+            reviews_received: 0,
+            rating_total: 0,
+            bump: 0,
+            bond_locked_until: 0,
+        }
+    }
+
+    #[test]
+    fn only_rental_parties_can_dispute() {
+        let rental = rental();
+        assert!(rental.is_party(&rental.renter));
+        assert!(rental.is_party(&rental.owner_wallet));
+        assert!(!rental.is_party(&Pubkey::new_unique()));
+    }
+
+    #[test]
+    fn profile_is_initialized_once() {
+        let mut profile = profile();
+        let wallet = Pubkey::new_unique();
+        profile.init_if_empty(wallet, 253);
+        profile.init_if_empty(Pubkey::new_unique(), 1);
+        assert_eq!(profile.wallet, wallet);
+        assert_eq!(profile.bump, 253);
+    }
+
+    #[test]
+    fn cancellation_count_overflow_is_an_error() {
+        let mut profile = profile();
+        profile.record_cancellation().unwrap();
+        assert_eq!(profile.cancellations, 1);
+        profile.cancellations = u64::MAX;
+        assert!(profile.record_cancellation().is_err());
+    }
+
+    #[test]
+    fn largest_rental_fits_its_space() {
+        let mut rental = rental();
+        rental.booking_id = "b".repeat(64);
+        rental.completed_at = Some(3_000);
+        rental.resolution_reason = Some("r".repeat(256));
+        rental.dispute_opened_by = Some(Pubkey::new_unique());
+        rental.payout_splits = vec![
+            PayoutSplit { recipient: Pubkey::new_unique(), bps: 100 };
+            MAX_PAYOUT_SPLITS
+        ];
+        rental.referrer = Some(Pubkey::new_unique());
+        rental.category_id = Some(3);
+        rental.coupon = Some(Pubkey::new_unique());
+        rental.discount_bearer = Some(DiscountBearer::Platform);
+        rental.balance_due_at = Some(5_000);
+        rental.early_return_requested_at = Some(50_000);
+        rental.handed_over_at = Some(10_000);
+        rental.handover_recorded_by = Some(Pubkey::new_unique());
+        rental.no_show_claimed_by = Some(Pubkey::new_unique());
+        rental.no_show_claimed_at = Some(20_000);
+        let mut data = Vec::new();
+        rental.try_serialize(&mut data).unwrap();
+        assert_eq!(data.len(), RentalTransaction::SPACE);
+    }
+
+    #[test]
+    fn migrated_legacy_rental_deserializes_with_new_fields_unset() {
+        let mut rental = rental();
+        rental.booking_id = "b".repeat(64);
+        rental.completed_at = Some(3_000);
+        rental.resolution_reason = Some("r".repeat(256));
+        let mut data = Vec::new();
+        rental.try_serialize(&mut data).unwrap();
+
+        // A legacy account ends where the fields added since then begin
+        data.truncate(RentalTransaction::LEGACY_SPACE);
+        assert!(RentalTransaction::try_deserialize(&mut &data[..]).is_err());
+
+        data.resize(RentalTransaction::SPACE, 0);
+        let migrated = RentalTransaction::try_deserialize(&mut &data[..]).unwrap();
+        assert_eq!(migrated.booking_id, rental.booking_id);
+        assert_eq!(migrated.total_amount, rental.total_amount);
+        assert!(migrated.dispute_opened_by.is_none());
+        assert_eq!(migrated.amount_paid, 0);
+    }
+
+    #[test]
+    fn legacy_escrowed_rentals_count_as_paid_in_full() {
+        let mut paid = rental();
+        paid.amount_paid = 0;
+        paid.policies.cancellation_policy = CancellationPolicy::Flexible;
+        paid.set_legacy_defaults();
+        assert_eq!(paid.amount_paid, paid.total_amount);
+        assert!(paid.policies == ProductPolicies::default());
+
+        let mut unpaid = rental();
+        unpaid.status = TransactionStatus::Created;
+        unpaid.amount_paid = 0;
+        unpaid.set_legacy_defaults();
+        assert_eq!(unpaid.amount_paid, 0);
+    }
+
+    #[test]
+    fn only_the_counterparty_is_reviewed() {
+        let rental = rental();
+        assert_eq!(rental.counterparty(&rental.renter), Some(rental.owner_wallet));
+        assert_eq!(rental.counterparty(&rental.owner_wallet), Some(rental.renter));
+        assert_eq!(rental.counterparty(&Pubkey::new_unique()), None);
+    }
+
+    #[test]
+    fn only_finished_rentals_are_reviewable() {
+        let mut rental = rental();
+        assert!(!rental.is_reviewable());
+        rental.status = TransactionStatus::Completed;
+        assert!(rental.is_reviewable());
+        rental.status = TransactionStatus::Resolved;
+        assert!(rental.is_reviewable());
+        rental.status = TransactionStatus::Settled;
+        assert!(rental.is_reviewable());
+        rental.status = TransactionStatus::Cancelled;
+        assert!(!rental.is_reviewable());
+    }
+
+    #[test]
+    fn payout_splits_floor_and_leave_dust_with_the_owner() {
+        let mut rental = rental();
+        rental.payout_splits = vec![
+            PayoutSplit { recipient: Pubkey::new_unique(), bps: 3333 },
+            PayoutSplit { recipient: Pubkey::new_unique(), bps: 1500 },
+        ];
+        let (split_amounts, owner_remainder) = rental.split_owner_proceeds(1_001).unwrap();
+        assert_eq!(split_amounts, vec![333, 150]);
+        assert_eq!(owner_remainder, 518);
+
+        rental.payout_splits.clear();
+        let (split_amounts, owner_remainder) = rental.split_owner_proceeds(1_001).unwrap();
+        assert!(split_amounts.is_empty());
+        assert_eq!(owner_remainder, 1_001);
+    }
+
+    #[test]
+    fn referral_share_comes_out_of_the_platform_fee() {
+        let mut rental = rental();
+        assert_eq!(rental.split_referral_fee(1_000, 2_500).unwrap(), (0, 1_000));
+
+        rental.referrer = Some(Pubkey::new_unique());
+        assert_eq!(rental.split_referral_fee(1_000, 2_500).unwrap(), (250, 750));
+        assert_eq!(rental.split_referral_fee(999, 3_333).unwrap(), (332, 667));
+        assert_eq!(rental.split_referral_fee(1_000, 0).unwrap(), (0, 1_000));
+    }
+
+    #[test]
+    fn owners_get_the_highest_fee_tier_they_qualify_for() {
+        let fee_tiers = FeeTierConfig {
+            tiers: vec![
+                FeeTier { min_volume: 1_000, platform_fee_rate: 800 },
+                FeeTier { min_volume: 10_000, platform_fee_rate: 500 },
+            ],
+            bump: 255,
+        };
+        assert_eq!(fee_tiers.rate_for_volume(999), None);
+        assert_eq!(fee_tiers.rate_for_volume(1_000), Some(800));
+        assert_eq!(fee_tiers.rate_for_volume(9_999), Some(800));
+        assert_eq!(fee_tiers.rate_for_volume(50_000), Some(500));
+    }
+
+    #[test]
+    fn category_rate_replaces_the_global_rate_and_tiers_only_lower_it() {
+        let global_state = global_state();
+        let category_fee = CategoryFeeConfig { category_id: 3, platform_fee_rate: 700, bump: 255 };
+        let fee_tiers = FeeTierConfig {
+            tiers: vec![
+                FeeTier { min_volume: 1_000, platform_fee_rate: 800 },
+                FeeTier { min_volume: 10_000, platform_fee_rate: 500 },
+            ],
+            bump: 255,
+        };
+
+        assert_eq!(resolve_platform_fee_rate(&global_state, None, None, 0), 1_000);
+        assert_eq!(resolve_platform_fee_rate(&global_state, Some(&category_fee), None, 0), 700);
+        assert_eq!(resolve_platform_fee_rate(&global_state, None, Some(&fee_tiers), 1_000), 800);
+        assert_eq!(resolve_platform_fee_rate(&global_state, Some(&category_fee), Some(&fee_tiers), 1_000), 700);
+        assert_eq!(resolve_platform_fee_rate(&global_state, Some(&category_fee), Some(&fee_tiers), 10_000), 500);
+    }
+
+    #[test]
+    fn fees_are_clamped_to_the_mint_bounds_and_the_charged_amount() {
+        let fee_clamp = FeeClampConfig { mint: Pubkey::new_unique(), min_fee: 50, max_fee: 500, bump: 255 };
+        let uncapped = FeeClampConfig { max_fee: 0, ..fee_clamp.clone() };
+
+        assert_eq!(clamp_fee(10, 10_000, None), 10);
+        assert_eq!(clamp_fee(10, 10_000, Some(&fee_clamp)), 50);
+        assert_eq!(clamp_fee(900, 10_000, Some(&fee_clamp)), 500);
+        assert_eq!(clamp_fee(900, 10_000, Some(&uncapped)), 900);
+        assert_eq!(clamp_fee(10, 30, Some(&fee_clamp)), 30);
+    }
+
+    #[test]
+    fn product_rates_price_days_weekends_partial_days_and_discounts() {
+        // 1970-01-05 was a Monday
+        let monday = 4 * SECONDS_PER_DAY;
+        let rates = ProductRates {
+            daily_rate: 100,
+            hourly_rate: 10,
+            weekly_rate: 0,
+            weekly_discount_bps: 1_000,
+            monthly_discount_bps: 0,
+            weekend_multiplier_bps: 15_000,
+        };
+
+        let two_days_three_hours = 2 * SECONDS_PER_DAY + 3 * SECONDS_PER_HOUR;
+        assert_eq!(rates.quote_price(monday, monday + two_days_three_hours).unwrap(), 230);
+        // A partial day never costs more than a full one
+        assert_eq!(rates.quote_price(monday, monday + 23 * SECONDS_PER_HOUR).unwrap(), 100);
+        // Friday and Saturday
+        let friday = monday - 3 * SECONDS_PER_DAY;
+        assert_eq!(rates.quote_price(friday, friday + 2 * SECONDS_PER_DAY).unwrap(), 250);
+
+        let weekly = ProductRates { weekly_rate: 600, ..rates.clone() };
+        assert_eq!(weekly.quote_price(monday, monday + 8 * SECONDS_PER_DAY).unwrap(), 630);
+
+        assert!(rates.quote_price(monday, monday).is_err());
+    }
+    // Lays out an ed25519 program instruction the way the web3 helpers do, with the
+    // signature left zeroed since the runtime verifies it before the program runs
+    fn ed25519_data(signer: &Pubkey, message: &[u8], instruction_index: u16) -> Vec<u8> {
+        let public_key_offset: u16 = 16;
+        let signature_offset = public_key_offset + 32;
+        let message_offset = signature_offset + 64;
+        let mut data = vec![1, 0];
+        for value in [
+            signature_offset,
+            instruction_index,
+            public_key_offset,
+            instruction_index,
+            message_offset,
+            message.len() as u16,
+            instruction_index,
+        ] {
+            data.extend_from_slice(&value.to_le_bytes());
+        }
+        data.extend_from_slice(signer.as_ref());
+        data.extend_from_slice(&[0; 64]);
+        data.extend_from_slice(message);
+        data
+    }
+
+    #[test]
+    fn signed_quotes_must_come_from_the_quote_signer_and_match_the_quote() {
+        let signer = Pubkey::new_unique();
+        let quote = PriceQuote {
+            product_id: 7,
+            owner_wallet: Pubkey::new_unique(),
+            total_amount: 1_000,
+            rental_start: 10_000,
+            rental_end: 100_000,
+            category_id: None,
+            expires_at: 5_000,
+        };
+        let message = quote.message();
+
+        let data = ed25519_data(&signer, &message, u16::MAX);
+        assert!(check_ed25519_data(&data, &signer, &message).is_ok());
+        assert!(check_ed25519_data(&data, &Pubkey::new_unique(), &message).is_err());
+
+        let other_quote = PriceQuote { total_amount: 1, ..quote };
+        assert!(check_ed25519_data(&data, &signer, &other_quote.message()).is_err());
+
+        let referenced = ed25519_data(&signer, &message, 0);
+        assert!(check_ed25519_data(&referenced, &signer, &message).is_err());
+        assert!(check_ed25519_data(&data[..15], &signer, &message).is_err());
+    }
+
+    #[test]
+    fn owners_cannot_book_their_own_products() {
+        let renter = Pubkey::new_unique();
+        let owner = Pubkey::new_unique();
+        assert!(RentalTransaction::validate_booking(renter, owner, 1_000, 0, 10, "booking").is_ok());
+        assert!(RentalTransaction::validate_booking(owner, owner, 1_000, 0, 10, "booking").is_err());
+        assert!(RentalTransaction::validate_booking(renter, owner, 0, 0, 10, "booking").is_err());
+        assert!(RentalTransaction::validate_booking(renter, owner, 1_000, 10, 10, "booking").is_err());
+    }
+
+    #[test]
+    fn offers_are_bound_to_the_owner_signature_and_the_renter() {
+        let owner = Pubkey::new_unique();
+        let offer = OwnerOffer {
+            renter: Pubkey::new_unique(),
+            owner_wallet: owner,
+            product_id: 7,
+            total_amount: 1_000,
+            rental_start: 10_000,
+            rental_end: 100_000,
+            expires_at: 5_000,
+            nonce: 1,
+        };
+        let data = ed25519_data(&owner, &offer.message(), u16::MAX);
+        assert!(check_ed25519_data(&data, &owner, &offer.message()).is_ok());
+
+        let other_renter = OwnerOffer { renter: Pubkey::new_unique(), ..offer };
+        assert!(check_ed25519_data(&data, &owner, &other_renter.message()).is_err());
+    }
+
+    #[test]
+    fn service_fee_is_charged_on_top_and_clamped() {
+        let mut global_state = global_state();
+        assert_eq!(calculate_service_fee(10_000, &global_state, None).unwrap(), 0);
+
+        global_state.service_fee_rate = 500;
+        assert_eq!(calculate_service_fee(10_000, &global_state, None).unwrap(), 500);
+        let fee_clamp = FeeClampConfig { mint: Pubkey::new_unique(), min_fee: 0, max_fee: 300, bump: 255 };
+        assert_eq!(calculate_service_fee(10_000, &global_state, Some(&fee_clamp)).unwrap(), 300);
+    }
+    fn coupon(funded_by: DiscountBearer, discount: CouponDiscount) -> Coupon {
+        Coupon {
+            code: "SPRING".to_string(),
+            issuer: Pubkey::new_unique(),
+            funded_by,
+            discount,
+            max_uses: 10,
+            uses: 0,
+            per_wallet_limit: 1,
+            expires_at: 5_000,
+            product_id: None,
+            category_id: None,
+            bump: 255,
+        }
+    }
+
+    #[test]
+    fn coupon_discounts_never_exceed_the_price() {
+        let percent = coupon(DiscountBearer::Platform, CouponDiscount::Percent { bps: 1_500 });
+        assert_eq!(percent.discount_for(1_000).unwrap(), 150);
+        let fixed = coupon(DiscountBearer::Platform, CouponDiscount::Fixed { amount: 300 });
+        assert_eq!(fixed.discount_for(1_000).unwrap(), 300);
+        assert_eq!(fixed.discount_for(200).unwrap(), 200);
+    }
+
+    #[test]
+    fn coupons_are_limited_by_expiry_uses_scope_and_issuer() {
+        let mut rental = rental();
+        let mut coupon = coupon(DiscountBearer::Platform, CouponDiscount::Fixed { amount: 100 });
+        assert!(coupon.check_redeemable(&rental, 0, 5_000).is_ok());
+        assert!(coupon.check_redeemable(&rental, 0, 5_001).is_err());
+        assert!(coupon.check_redeemable(&rental, 1, 1_000).is_err());
+
+        coupon.uses = coupon.max_uses;
+        assert!(coupon.check_redeemable(&rental, 0, 1_000).is_err());
+        coupon.uses = 0;
+
+        coupon.product_id = Some(rental.product_id + 1);
+        assert!(coupon.check_redeemable(&rental, 0, 1_000).is_err());
+        coupon.product_id = None;
+        coupon.category_id = Some(3);
+        assert!(coupon.check_redeemable(&rental, 0, 1_000).is_err());
+        rental.category_id = Some(3);
+        assert!(coupon.check_redeemable(&rental, 0, 1_000).is_ok());
+
+        // Owners can only discount their own rentals
+        coupon.funded_by = DiscountBearer::Owner;
+        assert!(coupon.check_redeemable(&rental, 0, 1_000).is_err());
+        coupon.issuer = rental.owner_wallet;
+        assert!(coupon.check_redeemable(&rental, 0, 1_000).is_ok());
+    }
+
+    #[test]
+    fn owner_coupons_lower_the_price_and_platform_coupons_are_subsidized() {
+        let owner = coupon(DiscountBearer::Owner, CouponDiscount::Percent { bps: 2_000 });
+        assert_eq!(owner.apply_discount(1_000).unwrap(), (800, 0, 200));
+
+        let platform = coupon(DiscountBearer::Platform, CouponDiscount::Fixed { amount: 1_500 });
+        assert_eq!(platform.apply_discount(1_000).unwrap(), (0, 1_000, 1_000));
+    }
+
+    #[test]
+    fn cancellation_policies_refund_by_notice_given() {
+        let day = SECONDS_PER_DAY;
+        assert_eq!(CancellationPolicy::Flexible.refund_rate(1), 10_000);
+        assert_eq!(CancellationPolicy::Flexible.refund_rate(0), 0);
+        assert_eq!(CancellationPolicy::Moderate.refund_rate(day), 10_000);
+        assert_eq!(CancellationPolicy::Moderate.refund_rate(day - 1), 5_000);
+        assert_eq!(CancellationPolicy::Strict.refund_rate(7 * day), 10_000);
+        assert_eq!(CancellationPolicy::Strict.refund_rate(day), 5_000);
+        assert_eq!(CancellationPolicy::Strict.refund_rate(day - 1), 0);
+    }
+
+    #[test]
+    fn renter_cancellation_refunds_the_service_fee_only_in_full() {
+        let mut rental = rental();
+        rental.service_fee = 50_000;
+
+        let early = rental.renter_cancellation(rental.rental_start - SECONDS_PER_DAY, None).unwrap();
+        assert_eq!(early.refund_amount, 1_000_000);
+        assert_eq!(early.renter_payout, 1_050_000);
+        assert_eq!(early.cancellation_fee, 0);
+        assert_eq!(early.treasury_payout, 0);
+
+        let late = rental.renter_cancellation(rental.rental_start - 1, None).unwrap();
+        assert_eq!(late.refund_amount, 500_000);
+        assert_eq!(late.service_fee_refund, 0);
+        assert_eq!(late.renter_payout, 500_000);
+        assert_eq!(late.cancellation_fee, 500_000);
+        assert_eq!(late.treasury_payout, 550_000);
+
+        // The clamp caps the cancellation fee, which raises the refund
+        let fee_clamp = FeeClampConfig { mint: Pubkey::new_unique(), min_fee: 0, max_fee: 100_000, bump: 255 };
+        let clamped = rental.renter_cancellation(rental.rental_start - 1, Some(&fee_clamp)).unwrap();
+        assert_eq!(clamped.cancellation_fee, 100_000);
+        assert_eq!(clamped.refund_amount, 900_000);
+    }
+
+    #[test]
+    fn defaulted_deposits_are_refunded_under_the_policy() {
+        let mut rental = rental();
+        rental.status = TransactionStatus::PartiallyPaid;
+        rental.amount_paid = 300_000;
+        rental.service_fee = 50_000;
+
+        let cancellation = rental.renter_cancellation(rental.rental_start - 1, None).unwrap();
+        assert_eq!(cancellation.refund_amount, 150_000);
+        assert_eq!(cancellation.renter_payout, 150_000);
+        assert_eq!(cancellation.treasury_payout, 200_000);
+    }
+
+    #[test]
+    fn platform_subsidies_are_refunded_to_the_treasury_pro_rata() {
+        let mut rental = rental();
+        assert_eq!(rental.split_refund(400_000).unwrap(), (400_000, 0));
+
+        rental.discount_bearer = Some(DiscountBearer::Platform);
+        rental.discount_amount = 250_000;
+        assert_eq!(rental.split_refund(400_000).unwrap(), (300_000, 100_000));
+
+        rental.discount_bearer = Some(DiscountBearer::Owner);
+        assert_eq!(rental.split_refund(400_000).unwrap(), (400_000, 0));
+    }
+
+    #[test]
+    fn installments_split_the_price_and_schedule_evenly() {
+        let mut rental = rental();
+        rental.total_amount = 1_000_001;
+        rental.installment_count = 3;
+        assert_eq!(rental.installment_amount(0).unwrap(), 333_333);
+        assert_eq!(rental.installment_amount(2).unwrap(), 333_335);
+        assert_eq!(rental.installment_due_at(0).unwrap(), 10_000);
+        assert_eq!(rental.installment_due_at(1).unwrap(), 40_000);
+        assert_eq!(rental.installment_due_at(2).unwrap(), 70_000);
+    }
+
+    #[test]
+    fn only_ended_installment_periods_are_released() {
+        let mut rental = rental();
+        rental.status = TransactionStatus::PartiallyPaid;
+        rental.installment_count = 3;
+        rental.installments_paid = 2;
+
+        // Paying the second installment early must not release the first period
+        assert_eq!(rental.ended_installments_total(39_999).unwrap(), 0);
+        assert_eq!(rental.ended_installments_total(40_000).unwrap(), 333_333);
+        // The second period has been paid but has not ended yet
+        assert_eq!(rental.ended_installments_total(69_999).unwrap(), 333_333);
+        assert_eq!(rental.ended_installments_total(70_000).unwrap(), 666_666);
+
+        rental.installments_paid = 3;
+        assert_eq!(rental.ended_installments_total(99_999).unwrap(), 666_666);
+        assert_eq!(rental.ended_installments_total(100_000).unwrap(), 1_000_000);
+    }
+
+    #[test]
+    fn installment_plans_can_be_disputed_mid_schedule() {
+        let mut rental = rental();
+        assert!(rental.is_disputable());
+
+        rental.status = TransactionStatus::PartiallyPaid;
+        assert!(!rental.is_disputable());
+
+        rental.installment_count = 3;
+        assert!(rental.is_disputable());
+
+        rental.status = TransactionStatus::Created;
+        assert!(!rental.is_disputable());
+    }
+
+    #[test]
+    fn released_platform_fees_do_not_depend_on_how_often_earnings_are_released() {
+        let mut rental = rental();
+        let mut collected = 0;
+        for _ in 0..3 {
+            let fee = rental.release_platform_fee(333_333, 1_000, None).unwrap();
+            rental.released_amount += 333_333;
+            rental.released_fee += fee;
+            collected += fee;
+        }
+        let last_fee = rental.release_platform_fee(1, 1_000, None).unwrap();
+        assert_eq!(collected + last_fee, 100_000);
+
+        // Each release still shares its fee with the referrer
+        rental.referrer = Some(Pubkey::new_unique());
+        assert_eq!(rental.split_referral_fee(33_333, 2_000).unwrap(), (6_666, 26_667));
+    }
+
+    #[test]
+    fn earnings_accrue_linearly_up_to_what_has_been_paid() {
+        let mut rental = rental();
+        assert_eq!(rental.accrued_amount(5_000).unwrap(), 0);
+        assert_eq!(rental.accrued_amount(55_000).unwrap(), 500_000);
+        assert_eq!(rental.accrued_amount(200_000).unwrap(), 1_000_000);
+
+        rental.amount_paid = 300_000;
+        assert_eq!(rental.accrued_amount(55_000).unwrap(), 300_000);
+    }
+
+    #[test]
+    fn early_returns_refund_unused_whole_days_under_the_policy() {
+        let mut rental = rental();
+        rental.rental_end = rental.rental_start + 10 * SECONDS_PER_DAY;
+        let policy = EarlyReturnPolicy { unused_day_refund_bps: 5_000, fee: 10_000 };
+
+        // Partial days are not refunded
+        let returned_at = rental.rental_end - 3 * SECONDS_PER_DAY - 1;
+        assert_eq!(policy.refund_for(&rental, returned_at).unwrap(), 140_000);
+        assert_eq!(policy.refund_for(&rental, rental.rental_end - 1).unwrap(), 0);
+        assert_eq!(EarlyReturnPolicy::default().refund_for(&rental, rental.rental_start).unwrap(), 0);
+
+        // The fee never turns a refund into a charge
+        let fee_only = EarlyReturnPolicy { unused_day_refund_bps: 100, fee: 10_000 };
+        assert_eq!(fee_only.refund_for(&rental, rental.rental_end - SECONDS_PER_DAY).unwrap(), 0);
+
+        rental.policies.early_return = EarlyReturnPolicy { unused_day_refund_bps: 10_001, fee: 0 };
+        assert!(rental.policies.validate().is_err());
+    }
+
+    #[test]
+    fn earnings_stop_accruing_once_an_early_return_is_requested() {
+        let mut rental = rental();
+        rental.early_return_requested_at = Some(55_000);
+        assert_eq!(rental.accrued_amount(40_000).unwrap(), 333_333);
+        assert_eq!(rental.accrued_amount(90_000).unwrap(), 500_000);
+    }
+
+    #[test]
+    fn mutual_settlements_split_what_is_still_in_escrow() {
+        let mut rental = rental();
+        rental.service_fee = 50_000;
+        rental.released_amount = 200_000;
+
+        let settlement = rental.settlement(2_500).unwrap();
+        assert_eq!(settlement.renter_refund, 200_000);
+        assert_eq!(settlement.owner_share, 600_000);
+        assert_eq!(settlement.service_fee_refund, 12_500);
+        assert_eq!(settlement.service_fee_retained, 37_500);
+
+        let full_refund = rental.settlement(10_000).unwrap();
+        assert_eq!(full_refund.owner_share, 0);
+        assert_eq!(full_refund.service_fee_retained, 0);
+    }
+
+    #[test]
+    fn owner_refunds_come_out_of_the_owners_share_of_escrow() {
+        let mut rental = rental();
+        rental.released_amount = 200_000;
+        rental.refunded_amount = 100_000;
+        assert_eq!(rental.escrowed_amount().unwrap(), 700_000);
+        // Refunds lower the earnings that accrue over the rest of the rental
+        assert_eq!(rental.accrued_amount(55_000).unwrap(), 450_000);
+        assert_eq!(rental.settlement(5_000).unwrap().renter_refund, 350_000);
+
+        rental.refunded_amount = 900_000;
+        assert!(rental.escrowed_amount().is_err());
+    }
+
+    #[test]
+    fn only_untouched_paid_rentals_before_the_start_can_be_rescheduled() {
+        let rental = rental();
+        assert!(rental.require_reschedulable(rental.rental_start - 1).is_ok());
+        assert!(rental.require_reschedulable(rental.rental_start).is_err());
+
+        let mut partially_paid = rental.clone();
+        partially_paid.status = TransactionStatus::PartiallyPaid;
+        assert!(partially_paid.require_reschedulable(0).is_err());
+
+        let mut released = rental.clone();
+        released.released_amount = 1;
+        assert!(released.require_reschedulable(0).is_err());
+
+        let mut refunded = rental.clone();
+        refunded.refunded_amount = 1;
+        assert!(refunded.require_reschedulable(0).is_err());
+
+        let mut discounted = rental.clone();
+        discounted.coupon = Some(Pubkey::new_unique());
+        assert!(discounted.require_reschedulable(0).is_err());
+    }
+
+    #[test]
+    fn only_the_next_amendment_on_the_restated_terms_is_accepted() {
+        let mut rental = rental();
+        rental.status = TransactionStatus::Created;
+        let amendment = BookingAmendment {
+            rental_transaction: Pubkey::new_unique(),
+            owner: rental.owner_wallet,
+            amendment_number: 1,
+            total_amount: 900_000,
+            rental_start: 20_000,
+            rental_end: 90_000,
+            proposed_at: 1_000,
+            bump: 255,
+        };
+        assert!(amendment.matches(&rental, 900_000, 20_000, 90_000));
+        assert!(!amendment.matches(&rental, 800_000, 20_000, 90_000));
+        assert!(!amendment.matches(&rental, 900_000, 20_000, 95_000));
+
+        // A stale proposal cannot be replayed once a later one was accepted
+        rental.amendment_count = 1;
+        assert!(!amendment.matches(&rental, 900_000, 20_000, 90_000));
+    }
+
+    #[test]
+    fn late_owner_cancellations_cost_a_share_of_the_price() {
+        let mut global_state = global_state();
+        global_state.owner_penalty_rate = 2_000;
+        let rental = rental();
+        let window_start = rental.rental_start - global_state.owner_penalty_window;
+
+        let penalty = |time| global_state.owner_cancellation_penalty(&rental, time).unwrap();
+        assert_eq!(penalty(window_start), 0);
+        assert_eq!(penalty(window_start + 1), 200_000);
+    }
+
+    #[test]
+    fn bonds_stay_locked_until_the_latest_booking_starts() {
+        let mut profile = profile();
+        profile.lock_bond_until(50_000);
+        profile.lock_bond_until(20_000);
+        assert_eq!(profile.bond_locked_until, 50_000);
+        profile.lock_bond_until(80_000);
+        assert_eq!(profile.bond_locked_until, 80_000);
+    }
+
+    #[test]
+    fn migrated_legacy_profile_deserializes_with_the_bond_unlocked() {
+        let mut profile = profile();
+        profile.wallet = Pubkey::new_unique();
+        profile.bond_locked_until = 50_000;
+        let mut data = Vec::new();
+        profile.try_serialize(&mut data).unwrap();
+        assert_eq!(data.len(), UserProfile::SPACE);
+
+        data.truncate(UserProfile::LEGACY_SPACE);
+        assert!(UserProfile::try_deserialize(&mut &data[..]).is_err());
+
+        data.resize(UserProfile::SPACE, 0);
+        let migrated = UserProfile::try_deserialize(&mut &data[..]).unwrap();
+        assert_eq!(migrated.wallet, profile.wallet);
+        assert_eq!(migrated.bond_locked_until, 0);
+    }
+
+    #[test]
+    fn owner_recorded_handovers_are_confirmed_once_the_contest_window_closes() {
+        let window = SECONDS_PER_DAY;
+        let mut rental = rental();
+        assert!(!rental.is_handover_confirmed(20_000, window).unwrap());
+
+        rental.handed_over_at = Some(20_000);
+        rental.handover_recorded_by = Some(rental.owner_wallet);
+        assert!(!rental.is_handover_confirmed(20_000 + window, window).unwrap());
+        assert!(rental.is_handover_confirmed(20_000 + window + 1, window).unwrap());
+
+        rental.handover_recorded_by = Some(rental.renter);
+        assert!(rental.is_handover_confirmed(20_000, window).unwrap());
+    }
+
+    #[test]
+    fn only_the_other_party_can_contest_a_claim_while_the_window_is_open() {
+        let window = SECONDS_PER_DAY;
+        let rental = rental();
+        let claimed_by = Some(rental.owner_wallet);
+        let contest = |signer: &Pubkey, time| {
+            rental.require_contestable_by(signer, claimed_by, Some(20_000), window, time)
+        };
+
+        assert!(contest(&rental.renter, 20_000 + window).is_ok());
+        assert!(contest(&rental.renter, 20_000 + window + 1).is_err());
+        assert!(contest(&rental.owner_wallet, 20_000).is_err());
+        assert!(contest(&Pubkey::new_unique(), 20_000).is_err());
+    }
+
+    #[test]
+    fn no_shows_are_claimable_once_the_window_passes_without_a_handover() {
+        let window = SECONDS_PER_DAY;
+        let mut rental = rental();
+        assert!(rental.require_no_show_claimable(rental.rental_start + window - 1, window).is_err());
+        assert!(rental.require_no_show_claimable(rental.rental_start + window, window).is_ok());
+
+        // Earnings already released do not block a claim
+        rental.status = TransactionStatus::PartiallyPaid;
+        rental.released_amount = 100_000;
+        assert!(rental.require_no_show_claimable(rental.rental_start + window, window).is_ok());
+
+        rental.handed_over_at = Some(rental.rental_start);
+        assert!(rental.require_no_show_claimable(rental.rental_start + window, window).is_err());
+    }
+
+    #[test]
+    fn no_shows_settle_the_escrow_by_who_failed_to_show() {
+        let mut rental = rental();
+        rental.service_fee = 50_000;
+        rental.refunded_amount = 100_000;
+        rental.policies.renter_no_show_owner_share_bps = 3_000;
+
+        // The owner never handed over: the renter gets the escrow and the service fee
+        let owner_no_show = rental.no_show_settlement(&rental.renter).unwrap();
+        assert_eq!(owner_no_show.renter_refund, 900_000);
+        assert_eq!(owner_no_show.owner_share, 0);
+        assert_eq!(owner_no_show.service_fee_refund, 50_000);
+
+        // The renter never picked up: the owner keeps the policy share of the escrow
+        let renter_no_show = rental.no_show_settlement(&rental.owner_wallet).unwrap();
+        assert_eq!(renter_no_show.owner_share, 270_000);
+        assert_eq!(renter_no_show.renter_refund, 630_000);
+        assert_eq!(renter_no_show.service_fee_refund, 0);
+        assert_eq!(renter_no_show.service_fee_retained, 50_000);
+    }
 }
\ No newline at end of file